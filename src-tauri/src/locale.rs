@@ -0,0 +1,47 @@
+// 托盘菜单文案，按 language 设置选择；至少提供英文和简体中文
+pub struct TrayLabels {
+    pub new_note: &'static str,
+    pub show_notes: &'static str,
+    pub settings: &'static str,
+    pub archive: &'static str,
+    pub quit: &'static str,
+}
+
+const EN_LABELS: TrayLabels = TrayLabels {
+    new_note: "New Note",
+    show_notes: "Show Notes",
+    settings: "Settings",
+    archive: "Archive",
+    quit: "Quit",
+};
+
+const ZH_CN_LABELS: TrayLabels = TrayLabels {
+    new_note: "新建便签",
+    show_notes: "显示便签",
+    settings: "设置",
+    archive: "归档",
+    quit: "退出",
+};
+
+// 把 "system" 解析为具体语言，与前端 i18n.js 支持的语言保持一致
+pub fn resolve_language(preference: &str) -> &'static str {
+    match preference {
+        "en" => "en",
+        "zh-CN" => "zh-CN",
+        _ => {
+            let system_locale = sys_locale::get_locale().unwrap_or_default().to_lowercase();
+            if system_locale.starts_with("zh") {
+                "zh-CN"
+            } else {
+                "en"
+            }
+        }
+    }
+}
+
+pub fn tray_labels(preference: &str) -> &'static TrayLabels {
+    match resolve_language(preference) {
+        "zh-CN" => &ZH_CN_LABELS,
+        _ => &EN_LABELS,
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::prelude::*;
+
+use crate::models::RecentError;
+
+const LOG_FILE_NAME: &str = "fadenote.log";
+const MAX_LOG_FILES: usize = 7;
+// 内存中保留的最近错误条数
+const MAX_RECENT_ERRORS: usize = 50;
+
+// 最近的 ERROR 级别日志，供前端通过 get_recent_errors 查看
+pub type RecentErrors = Arc<Mutex<VecDeque<RecentError>>>;
+
+// 把 ERROR 级别事件记入环形缓冲区，超出上限时丢弃最旧的一条
+struct RecentErrorLayer {
+    errors: RecentErrors,
+}
+
+impl<S: Subscriber> Layer<S> for RecentErrorLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if *event.metadata().level() != Level::ERROR {
+            return;
+        }
+        let mut visitor = ErrorMessageVisitor::default();
+        event.record(&mut visitor);
+        let message = if visitor.fields.is_empty() {
+            visitor.message
+        } else {
+            format!("{} ({})", visitor.message, visitor.fields.join(", "))
+        };
+
+        let mut errors = self.errors.lock().unwrap();
+        if errors.len() >= MAX_RECENT_ERRORS {
+            errors.pop_front();
+        }
+        errors.push_back(RecentError {
+            at: chrono::Local::now().to_rfc3339(),
+            message,
+        });
+    }
+}
+
+// 收集事件的消息和其余字段（key=value）
+#[derive(Default)]
+struct ErrorMessageVisitor {
+    message: String,
+    fields: Vec<String>,
+}
+
+impl Visit for ErrorMessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields.push(format!("{}={}", field.name(), value));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.fields.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+}
+
+// 设置中的日志级别，未知值按 info 处理
+fn level_from_setting(value: &str) -> LevelFilter {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "off" => LevelFilter::OFF,
+        "error" => LevelFilter::ERROR,
+        "warn" => LevelFilter::WARN,
+        "debug" => LevelFilter::DEBUG,
+        "trace" => LevelFilter::TRACE,
+        _ => LevelFilter::INFO,
+    }
+}
+
+// 同时输出到终端和 `logs/fadenote.log`（按天滚动，保留最近 7 份），ERROR 级别另外记入 recent_errors
+// 返回的 guard 需要在程序运行期间一直持有，否则缓冲的日志会丢失
+pub fn init_logging(app_data_dir: &Path, level: &str, recent_errors: RecentErrors) -> Option<WorkerGuard> {
+    let level = level_from_setting(level);
+    let stderr_layer = fmt::layer().with_writer(std::io::stderr);
+    let recent_error_layer = RecentErrorLayer { errors: recent_errors };
+
+    let file_appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_NAME)
+        .max_log_files(MAX_LOG_FILES)
+        .build(app_data_dir.join("logs"));
+
+    match file_appender {
+        Ok(file_appender) => {
+            let (file_writer, guard) = tracing_appender::non_blocking(file_appender);
+            tracing_subscriber::registry()
+                .with(level)
+                .with(stderr_layer)
+                .with(fmt::layer().with_ansi(false).with_writer(file_writer))
+                .with(recent_error_layer)
+                .init();
+            Some(guard)
+        }
+        Err(e) => {
+            tracing_subscriber::registry()
+                .with(level)
+                .with(stderr_layer)
+                .with(recent_error_layer)
+                .init();
+            tracing::warn!(error = %e, "创建日志文件失败，仅输出到终端");
+            None
+        }
+    }
+}
@@ -1,24 +1,44 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use std::time::Duration as StdDuration;
 
 use chrono::{Datelike, DateTime, Duration, Local, Timelike, Utc};
-use tauri::{Emitter, Manager, menu::{MenuBuilder, MenuItem}, tray::TrayIconBuilder};
+use tauri::{Emitter, Manager, menu::{Menu, MenuBuilder, MenuItem}, tray::TrayIconBuilder};
+use tauri_plugin_autostart::{MacosLauncher, ManagerExt};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
+mod locale;
+mod logging;
 mod models;
 mod note_content;
+mod protection;
 mod storage;
 
-use models::{AppInfo, FileInfo, IndexFile, NoteEntry, ScheduleSettings, WindowInfo};
+use locale::tray_labels;
+use logging::{init_logging, RecentErrors};
+use models::{AppInfo, AppInfoSummary, ArchiveRow, BodyEncryption, BulkTagsReport, CleanExportEntry, CleanExportManifest, DateBucket, FadeAnchor, FolderGranularity, FolderSummary, ImportReport, IndexChange, IndexFile, IndexIssue, IndexProblem, LayoutWindow, LoadedNote, NoteColors, NoteEntry, NoteEvent, NoteLayout, NoteTags, NoteUrgency, NoteWindowState, OrphanFile, QuickSwitchItem, RecentError, RepairPathsReport, ScheduleSettings, StartupBehavior, StartupReport, StorageUsage, WindowInfo};
+use protection::{
+    check_passphrase_verifier, decrypt_body, derive_body_key, encrypt_body, encrypted_body_salt,
+    hash_passphrase, new_encryption_salt, new_passphrase_verifier, salt_from_hex,
+    salt_to_hex, verify_passphrase,
+};
 use note_content::{
-    build_full_content, extract_content_only, extract_created_at_from_content,
-    extract_first_line_preview, parse_id_from_content,
+    body_sha256, build_full_content, content_hash, extract_content_only, extract_created_at_from_content,
+    extract_preview, markdown_to_plaintext, parse_id_from_content, render_markdown_html, render_plaintext_html,
+    strip_leading_front_matter, word_count,
+    FrontMatterStyle,
+};
+use storage::{
+    collect_file_sizes, collect_markdown_files, copy_dir_recursive, get_app_data_dir, resolve_relative_path,
+    sanitize_slug, set_custom_data_dir, system_app_data_dir, unique_markdown_path, write_file_safely,
 };
-use storage::{get_app_data_dir, write_file_safely};
 
 // 检查是否为首次启动
 // 条件：index.json不存在或为空，且notes目录下没有任何md文件
@@ -83,7 +103,32 @@ fn is_first_launch(app_data_dir: &Path) -> bool {
 }
 
 // 获取首次启动欢迎文案
+// 开启 rotateWelcome 时，从内置提示和数据目录下 tips.txt（以空行分隔的段落，每段一条）中随机挑选一条
 fn get_welcome_content() -> String {
+    if !load_schedule_settings_from_disk().rotate_welcome {
+        return default_welcome_content();
+    }
+
+    let mut tips: Vec<String> = WELCOME_TIPS.iter().map(|tip| tip.to_string()).collect();
+    if let Ok(content) = get_app_data_dir().and_then(|dir| fs::read_to_string(dir.join("tips.txt")).map_err(|e| e.to_string())) {
+        tips.extend(content.replace("\r\n", "\n")
+            .split("\n\n")
+            .map(|tip| tip.trim().to_string())
+            .filter(|tip| !tip.is_empty()));
+    }
+    let pick = (Uuid::new_v4().as_u128() % tips.len() as u128) as usize;
+    tips.swap_remove(pick)
+}
+
+// 内置的欢迎提示
+const WELCOME_TIPS: [&str; 4] = [
+    "便签会在一段时间不编辑后淡出。\n\n固定的便签不会淡出。",
+    "淡出的便签不会被删除，\n可以从托盘的「归档」里找回来。",
+    "每次编辑都会让便签重新\"活\"过来，\n过期时间从编辑时重新计算。",
+    "便签就是普通的 Markdown 文件，\n可以在数据目录里用任何编辑器打开。",
+];
+
+fn default_welcome_content() -> String {
     "写点什么吧。
 
 这张便签会自动保存。
@@ -96,10 +141,119 @@ fn get_welcome_content() -> String {
 可以从托盘里再叫回来。".to_string()
 }
 
+// 当前支持的索引格式版本（V2）
+const INDEX_VERSION: u32 = 2;
+
+// 托盘图标ID，用于语言切换后重建菜单
+const TRAY_ID: &str = "fadenote-tray";
+
+// 便签窗口的最小尺寸，避免窗口被拖成一条细线后找不回来
+const NOTE_MIN_WIDTH: f64 = 200.0;
+const NOTE_MIN_HEIGHT: f64 = 120.0;
+
+// 新便签默认的窗口位置和大小（未在设置中配置时使用）
+const DEFAULT_NOTE_X: f64 = 200.0;
+const DEFAULT_NOTE_Y: f64 = 200.0;
+const DEFAULT_NOTE_WIDTH: f64 = 280.0;
+const DEFAULT_NOTE_HEIGHT: f64 = 360.0;
+// 开启层叠时每张便签的偏移量及循环周期
+const CASCADE_OFFSET: f64 = 24.0;
+const CASCADE_STEPS: usize = 8;
+// 归档便签只读预览窗口的标签后缀：note-{id}-peek
+const PEEK_LABEL_SUFFIX: &str = "-peek";
+// 网格排列时窗口之间及与屏幕边缘的间距
+const GRID_GAP: f64 = 16.0;
+// arrange_windows 层叠排列时相邻窗口的偏移量
+const ARRANGE_CASCADE_OFFSET: f64 = 30.0;
+// 贴边隐藏时留在屏幕内的像素，以及滑入/滑出动画的帧数和每帧间隔
+const AUTO_HIDE_PEEK: i32 = 4;
+const AUTO_HIDE_STEPS: i32 = 8;
+const AUTO_HIDE_FRAME: StdDuration = StdDuration::from_millis(12);
+
+// 活跃便签正文的内容哈希缓存：文件路径 -> ((修改时间, 文件大小), 哈希)
+type ContentHashCache = std::collections::HashMap<PathBuf, ((std::time::SystemTime, u64), String)>;
+
+// 等待便签回写的调用：便签ID -> (调用ID, 通知通道)
+type FlushWaiters = std::collections::HashMap<String, Vec<(Uuid, std::sync::mpsc::Sender<String>)>>;
+
 // V2规范的数据模型
 // 应用状态
 struct AppState {
     notes_directory: Mutex<Option<PathBuf>>,
+    last_action: Mutex<Option<UndoAction>>,
+    // 处于放大状态的便签及其放大前的基础尺寸 (width, height)
+    zoomed_notes: Mutex<std::collections::HashMap<String, (f64, f64)>>,
+    // 专注模式下被隐藏的便签窗口标签，退出时只恢复这些窗口
+    focus_hidden_labels: Mutex<Vec<String>>,
+    // 启动检查摘要，前端取走一次后清空
+    startup_report: Mutex<Option<StartupReport>>,
+    // 等待便签回写的调用：便签ID -> (调用ID, 通知通道)，保存成功后逐个通知并移除
+    flush_waiters: Mutex<FlushWaiters>,
+    // active_content_hashes 的缓存，文件未变化时不再重新读取
+    content_hashes: Mutex<ContentHashCache>,
+    // 被 pin_expiring_notes 临时固定的便签 -> 自动取消的时间，撤销时只恢复这些
+    temporary_pins: Mutex<std::collections::HashMap<String, DateTime<Local>>>,
+    // 贴边动画的序号：窗口标签 -> 最新一次动画的序号，旧动画发现序号变化后停止
+    slide_generations: Mutex<std::collections::HashMap<String, u64>>,
+    // 正在闪现的便签：标签 -> (本次闪现的序号, 闪现前的置顶状态)
+    flashing_notes: Mutex<std::collections::HashMap<String, (u64, bool)>>,
+    // 已解锁的受保护便签：ID -> 解锁会话
+    unlocked_notes: Mutex<std::collections::HashMap<String, UnlockedNote>>,
+    // 当前处于最小化状态的便签窗口标签，「显示便签」时视为隐藏
+    minimized_labels: Mutex<std::collections::HashSet<String>>,
+    // 正在因关闭而归档的便签ID，避免重复的关闭请求重复处理
+    closing_notes: Mutex<std::collections::HashSet<String>>,
+    // 日志中最近的错误，与日志系统共享
+    recent_errors: RecentErrors,
+    // 全局正文加密的密钥，输入口令后只保存在内存中
+    encryption_key: Mutex<Option<[u8; 32]>>,
+}
+
+// 一次解锁发放的令牌；正文加密的便签同时保存由口令派生的密钥，超时后一并丢弃
+#[derive(Clone)]
+struct UnlockedNote {
+    token: String,
+    unlocked_at: std::time::Instant,
+    body_key: Option<[u8; 32]>,
+}
+
+// 撤销槽保留的时长，超时后被删除的文件内容也随之丢弃
+const UNDO_TIMEOUT: StdDuration = StdDuration::from_secs(60);
+// 受保护便签解锁令牌的有效期
+const UNLOCK_TIMEOUT: StdDuration = StdDuration::from_secs(5 * 60);
+// 每个便签保留的生命周期事件条数上限
+const MAX_NOTE_EVENTS: usize = 50;
+// save_all_now 等待各窗口回写的最长时间
+const SAVE_ALL_TIMEOUT: StdDuration = StdDuration::from_secs(3);
+
+// 单级撤销：记录最近一次恢复/归档/删除操作之前的状态
+enum UndoKind {
+    Restored { prior: NoteEntry },
+    Archived { prior: NoteEntry },
+    Deleted { prior: NoteEntry, file_bytes: Option<Vec<u8>> },
+}
+
+struct UndoAction {
+    kind: UndoKind,
+    recorded_at: std::time::Instant,
+}
+
+// 丢弃超时的撤销记录，释放被删除文件的内容
+fn clear_expired_undo_action(app_handle: &tauri::AppHandle) {
+    let app_state = app_handle.state::<AppState>();
+    let mut last_action = app_state.last_action.lock().unwrap();
+    if last_action.as_ref().is_some_and(|action| action.recorded_at.elapsed() > UNDO_TIMEOUT) {
+        *last_action = None;
+    }
+}
+
+fn record_undo_action(app_handle: &tauri::AppHandle, kind: UndoKind) {
+    let app_state = app_handle.state::<AppState>();
+    let mut last_action = app_state.last_action.lock().unwrap();
+    *last_action = Some(UndoAction {
+        kind,
+        recorded_at: std::time::Instant::now(),
+    });
 }
 
 // 获取当前ISO 8601时间戳
@@ -109,7 +263,7 @@ fn get_current_iso8601_time() -> String {
 
 fn new_empty_index() -> IndexFile {
     IndexFile {
-        version: 2,
+        version: INDEX_VERSION,
         app: AppInfo {
             name: "FadeNote".to_string(),
             created_at: get_current_iso8601_time(),
@@ -125,6 +279,15 @@ fn expire_at_7_days_from_iso(created_at: &str) -> Result<String, String> {
     Ok((created_time.with_timezone(&Local) + Duration::days(7)).to_rfc3339())
 }
 
+// 编辑或活动后新的过期时间：按 fadeAnchor 从现在或创建时间起算 7 天（createdAt 无法解析时从现在起算）
+fn renewed_expire_at(entry: &NoteEntry, now: &DateTime<Local>) -> String {
+    match entry.fade_anchor {
+        FadeAnchor::Created => expire_at_7_days_from_iso(&entry.created_at)
+            .unwrap_or_else(|_| (*now + Duration::days(7)).to_rfc3339()),
+        FadeAnchor::LastActive => (*now + Duration::days(7)).to_rfc3339(),
+    }
+}
+
 fn load_schedule_settings_from_disk() -> ScheduleSettings {
     let path = match get_app_data_dir() {
         Ok(dir) => dir.join("settings.json"),
@@ -137,6 +300,26 @@ fn load_schedule_settings_from_disk() -> ScheduleSettings {
     serde_json::from_str(&content).unwrap_or_default()
 }
 
+// 文件的创建时间；文件系统不支持创建时间时依次退回修改时间和当前时间，避免整个扫描失败
+fn file_created_time(created: std::io::Result<std::time::SystemTime>, modified: std::io::Result<std::time::SystemTime>, now: DateTime<Utc>) -> DateTime<Utc> {
+    created
+        .or(modified)
+        .map(DateTime::<Utc>::from)
+        .unwrap_or(now)
+}
+
+// 按设置中的预览长度/行数从正文（不含 Front Matter）生成预览
+// 所有写入 cachedPreview 的地方（创建、保存、扫描/重建）都通过这里生成，保证预览规则一致
+// 加密的正文不生成预览，由调用方按索引中的 encryption 标记跳过
+fn note_preview(body: &str, settings: &ScheduleSettings) -> Option<String> {
+    extract_preview(body, settings.preview_chars, settings.preview_lines)
+}
+
+// 当前设置下写入便签文件时使用的 Front Matter 风格
+fn front_matter_style(settings: &ScheduleSettings) -> FrontMatterStyle {
+    FrontMatterStyle::from_setting(&settings.front_matter)
+}
+
 fn save_schedule_settings_to_disk(settings: &ScheduleSettings) -> Result<(), String> {
     let app_data_dir = get_app_data_dir()?;
     fs::create_dir_all(&app_data_dir).map_err(|e| format!("create settings directory failed: {}", e))?;
@@ -146,6 +329,45 @@ fn save_schedule_settings_to_disk(settings: &ScheduleSettings) -> Result<(), Str
         .map_err(|e| format!("write settings failed: {}", e))
 }
 
+// 按 folderGranularity 设置确定某一天创建的便签文件所在目录
+fn dated_note_dir(notes_dir: &Path, date: chrono::NaiveDate, settings: &ScheduleSettings) -> PathBuf {
+    let notes_subdir = notes_dir.join("notes");
+    match settings.folder_granularity {
+        FolderGranularity::Daily => notes_subdir.join(date.format("%Y-%m-%d").to_string()),
+        FolderGranularity::Monthly => notes_subdir.join(date.format("%Y-%m").to_string()),
+        FolderGranularity::Flat => notes_subdir,
+    }
+}
+
+// 目录名是否为 dated_note_dir 生成的日期目录（YYYY-MM-DD 或 YYYY-MM）
+fn is_dated_folder_name(name: &str) -> bool {
+    chrono::NaiveDate::parse_from_str(name, "%Y-%m-%d").is_ok()
+        || chrono::NaiveDate::parse_from_str(&format!("{}-01", name), "%Y-%m-%d").is_ok()
+}
+
+// 根据设置计算新便签的默认窗口信息，开启层叠时按活跃便签数量偏移
+fn default_note_window_info(app_data_dir: &Path) -> WindowInfo {
+    let settings = load_schedule_settings_from_disk();
+    let offset = if settings.cascade {
+        let active_count = read_index(app_data_dir)
+            .map(|index| index.notes.iter().filter(|entry| is_active(entry)).count())
+            .unwrap_or(0);
+        (active_count % CASCADE_STEPS) as f64 * CASCADE_OFFSET
+    } else {
+        0.0
+    };
+
+    WindowInfo {
+        x: settings.default_note_x.unwrap_or(DEFAULT_NOTE_X) + offset,
+        y: settings.default_note_y.unwrap_or(DEFAULT_NOTE_Y) + offset,
+        width: settings.default_note_width.unwrap_or(DEFAULT_NOTE_WIDTH).max(NOTE_MIN_WIDTH),
+        height: settings.default_note_height.unwrap_or(DEFAULT_NOTE_HEIGHT).max(NOTE_MIN_HEIGHT),
+        decorated: false,
+        on_all_desktops: false,
+        monitor_index: None,
+    }
+}
+
 fn window_title_from_preview(preview: Option<&String>) -> String {
     match preview.map(|value| value.trim()).filter(|value| !value.is_empty()) {
         Some(preview) => format!("{} · FadeNote", preview.chars().take(40).collect::<String>()),
@@ -186,6 +408,48 @@ async fn raise_window_once(window: tauri::WebviewWindow) {
     }
 }
 
+// 把便签临时置顶并聚焦，seconds 秒后恢复闪现前的置顶状态
+// 重复闪现时保留最早记录的原始状态，只有最后一次闪现的计时器会执行恢复；窗口已关闭则不再处理
+#[tauri::command]
+async fn flash_note(window: tauri::WebviewWindow, id: String, seconds: u64) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let label = format!("note-{}", id);
+    let note_window = app_handle.get_webview_window(&label)
+        .ok_or_else(|| "便签窗口不存在".to_string())?;
+
+    let flash_token = {
+        let app_state = app_handle.state::<AppState>();
+        let mut flashing_notes = app_state.flashing_notes.lock().unwrap();
+        let was_always_on_top = match flashing_notes.get(&label) {
+            Some((_, was_always_on_top)) => *was_always_on_top,
+            None => note_window.is_always_on_top().unwrap_or(false),
+        };
+        let flash_token = flashing_notes.get(&label).map_or(0, |(token, _)| token + 1);
+        flashing_notes.insert(label.clone(), (flash_token, was_always_on_top));
+        flash_token
+    };
+
+    let _ = note_window.show();
+    let _ = note_window.unminimize();
+    note_window.set_always_on_top(true).map_err(|e| e.to_string())?;
+    let _ = note_window.set_focus();
+
+    std::thread::spawn(move || {
+        std::thread::sleep(StdDuration::from_secs(seconds));
+        let app_state = app_handle.state::<AppState>();
+        let mut flashing_notes = app_state.flashing_notes.lock().unwrap();
+        let was_always_on_top = match flashing_notes.get(&label) {
+            Some((token, was_always_on_top)) if *token == flash_token => *was_always_on_top,
+            _ => return,
+        };
+        flashing_notes.remove(&label);
+        if let Some(note_window) = app_handle.get_webview_window(&label) {
+            let _ = note_window.set_always_on_top(was_always_on_top);
+        }
+    });
+    Ok(())
+}
+
 async fn raise_active_notes_once_impl(app_handle: tauri::AppHandle) -> Result<(), String> {
     let app_data_dir = get_app_data_dir()?;
     let index = validate_and_fix_index(&app_data_dir)?;
@@ -215,11 +479,16 @@ async fn raise_active_notes_once_impl(app_handle: tauri::AppHandle) -> Result<()
 }
 
 // Fix 1: 引入「Domain Query 层」（纯判断）
+// 固定或设置为永不淡出的便签都不会过期
+fn never_fades(entry: &NoteEntry) -> bool {
+    entry.pinned || entry.never_expire
+}
+
 // 判断便签是否已归档
 // 判断便签是否过期
 fn is_expired_check(entry: &NoteEntry, now: &DateTime<Local>) -> bool {
-    // 如果便签被固定，则永远不会过期
-    if entry.pinned {
+    // 如果便签被固定或永不淡出，则永远不会过期
+    if never_fades(entry) {
         return false;
     }
     
@@ -234,6 +503,43 @@ fn is_expired_check(entry: &NoteEntry, now: &DateTime<Local>) -> bool {
     }
 }
 
+// 距离淡出还剩多少秒（已过期记为 0）；固定、永不淡出、没有或无法解析过期时间的便签返回 None，与 is_expired_check 的判断一致
+fn seconds_until_expiry(entry: &NoteEntry, now: &DateTime<Local>) -> Option<i64> {
+    if never_fades(entry) {
+        return None;
+    }
+    // 带时区的时间直接相减，不经过本地时间换算（夏令时切换时本地时间可能不存在或有歧义）
+    let expire_time = DateTime::parse_from_rfc3339(entry.expire_at.as_deref()?).ok()?;
+    Some(expire_time.signed_duration_since(*now).num_seconds().max(0))
+}
+
+// 便签在本轮寿命中已走过的比例（0.0–1.0）：从 lastActiveAt 到 expireAt 的进度
+// 固定、永不淡出或没有过期时间的便签为 0.0，已归档的便签为 1.0
+fn fade_fraction(entry: &NoteEntry, now: &DateTime<Local>) -> f64 {
+    if !is_active(entry) {
+        return 1.0;
+    }
+    if never_fades(entry) {
+        return 0.0;
+    }
+    let parse = |time: Option<&str>| DateTime::parse_from_rfc3339(time?).ok();
+    let (last_active, expire_time) = match (parse(Some(&entry.last_active_at)), parse(entry.expire_at.as_deref())) {
+        (Some(last_active), Some(expire_time)) => (last_active, expire_time),
+        _ => return 0.0,
+    };
+    let lifetime = (expire_time - last_active).num_seconds();
+    if lifetime <= 0 {
+        return 1.0;
+    }
+    let elapsed = (now.fixed_offset() - last_active).num_seconds();
+    (elapsed as f64 / lifetime as f64).clamp(0.0, 1.0)
+}
+
+// 是否为归档便签的只读预览窗口，这类窗口不参与保存、排列和关闭归档
+fn is_peek_label(label: &str) -> bool {
+    label.starts_with("note-") && label.ends_with(PEEK_LABEL_SUFFIX)
+}
+
 // 判断便签是否活跃
 fn is_active(entry: &NoteEntry) -> bool {
     entry.archived_at.is_none()
@@ -241,11 +547,30 @@ fn is_active(entry: &NoteEntry) -> bool {
 
 
 
+// 追加一条生命周期事件，只保留最近 MAX_NOTE_EVENTS 条；连续的编辑合并为一条
+fn record_note_event(entry: &mut NoteEntry, kind: &str, at: &str) {
+    if kind == "edited" {
+        if let Some(last_event) = entry.events.last_mut().filter(|event| event.kind == "edited") {
+            last_event.at = at.to_string();
+            return;
+        }
+    }
+    entry.events.push(NoteEvent {
+        kind: kind.to_string(),
+        at: at.to_string(),
+    });
+    if entry.events.len() > MAX_NOTE_EVENTS {
+        let overflow = entry.events.len() - MAX_NOTE_EVENTS;
+        entry.events.drain(..overflow);
+    }
+}
+
 // Fix 2: archive_note 作为唯一状态迁移入口
 fn archive_note(entry: &mut NoteEntry, now: &DateTime<Local>) -> Result<(), String> {
     // 只更新entry的归档状态和过期时间
     entry.archived_at = Some(now.to_rfc3339());
     entry.expire_at = None; // 归档后不再需要过期时间
+    record_note_event(entry, "archived", &now.to_rfc3339());
 
     Ok(())
 }
@@ -259,14 +584,133 @@ fn derive_status(entry: &mut NoteEntry) {
     };
 }
 
+// 冻结区间 (frozenAt, frozenUntil)，任一值缺失或无法解析时视为未冻结
+fn fading_freeze_window(settings: &ScheduleSettings) -> Option<(DateTime<Local>, DateTime<Local>)> {
+    let parse = |value: Option<&str>| DateTime::parse_from_rfc3339(value?).ok().map(|time| time.with_timezone(&Local));
+    Some((parse(settings.frozen_at.as_deref())?, parse(settings.frozen_until.as_deref())?))
+}
+
+// 冻结已到期时在 frozenUntil 时刻解冻并返回被顺延的便签ID；仍在冻结中返回 None，调用方应跳过所有淡出处理
+// 与 thaw_fading 一样只修改内存中的索引和设置
+fn thaw_lapsed_freeze(index: &mut IndexFile, settings: &mut ScheduleSettings, now: &DateTime<Local>) -> Option<Vec<String>> {
+    match fading_freeze_window(settings) {
+        Some((_, frozen_until)) if *now < frozen_until => None,
+        Some((_, frozen_until)) => Some(thaw_fading(index, settings, frozen_until)),
+        None => Some(Vec::new()),
+    }
+}
+
+// 解冻：把冻结期间暂停的时间补回到活跃、未固定便签的过期时间上，然后清除冻结设置，返回被顺延的便签ID
+// 每个便签补回的时长 = 解冻时刻 - max(冻结开始时间, 该便签的最后活跃时间)；
+// 冻结期间编辑或 touch 过的便签，过期时间已从那一刻重新计算，因此只补回之后仍处于冻结的那一段
+// 只修改内存中的索引和设置，由调用方先保存索引、再保存设置并发出通知
+fn thaw_fading(index: &mut IndexFile, settings: &mut ScheduleSettings, thawed_at: DateTime<Local>) -> Vec<String> {
+    let mut shifted_ids = Vec::new();
+    if let Some((frozen_at, _)) = fading_freeze_window(settings) {
+        for entry in index.notes.iter_mut().filter(|entry| is_active(entry) && !entry.pinned) {
+            let expire_time = match entry.expire_at.as_deref().map(DateTime::parse_from_rfc3339) {
+                Some(Ok(expire_time)) => expire_time,
+                _ => continue,
+            };
+            let paused_since = DateTime::parse_from_rfc3339(&entry.last_active_at)
+                .map(|last_active| last_active.with_timezone(&Local).max(frozen_at))
+                .unwrap_or(frozen_at);
+            let paused = thawed_at - paused_since;
+            if paused > Duration::zero() {
+                entry.expire_at = Some((expire_time + paused).to_rfc3339());
+                shifted_ids.push(entry.id.clone());
+            }
+        }
+    }
+
+    settings.frozen_until = None;
+    settings.frozen_at = None;
+    info!(note_ids = ?shifted_ids, "淡出已解冻，顺延过期时间");
+    shifted_ids
+}
+
+// 冻结淡出直到 until_iso：期间不归档、不清理任何便签；重复冻结时保留最初的冻结开始时间
+#[tauri::command]
+async fn freeze_fading(window: tauri::WebviewWindow, until_iso: String) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let until = DateTime::parse_from_rfc3339(&until_iso)
+        .map_err(|e| format!("无效的时间: {}", e))?;
+    let now = Local::now();
+    if until <= now {
+        return Err("冻结结束时间必须晚于当前时间".to_string());
+    }
+
+    // 上一次冻结已到期但还没解冻时，先解冻再开始新的冻结
+    let mut index = read_index(&notes_dir)?;
+    let mut settings = load_schedule_settings_from_disk();
+    let shifted_ids = thaw_lapsed_freeze(&mut index, &mut settings, &now).unwrap_or_default();
+    if !shifted_ids.is_empty() {
+        save_index(&notes_dir, &mut index)?;
+    }
+
+    if settings.frozen_at.is_none() {
+        settings.frozen_at = Some(now.to_rfc3339());
+    }
+    settings.frozen_until = Some(until.to_rfc3339());
+    save_schedule_settings_to_disk(&settings)?;
+    emit_index_changed(&app_handle, "updated", shifted_ids);
+    Ok(())
+}
+
+// 立即解冻淡出，按实际冻结的时长顺延活跃便签的过期时间，返回被顺延的便签ID
+#[tauri::command]
+async fn unfreeze_fading(window: tauri::WebviewWindow) -> Result<Vec<String>, String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut settings = load_schedule_settings_from_disk();
+    let thawed_at = match fading_freeze_window(&settings) {
+        Some((_, frozen_until)) => frozen_until.min(Local::now()),
+        None => return Ok(Vec::new()),
+    };
+
+    let mut index = read_index(&notes_dir)?;
+    let shifted_ids = thaw_fading(&mut index, &mut settings, thawed_at);
+    if !shifted_ids.is_empty() {
+        save_index(&notes_dir, &mut index)?;
+    }
+    save_schedule_settings_to_disk(&settings)?;
+    emit_index_changed(&app_handle, "updated", shifted_ids.clone());
+    Ok(shifted_ids)
+}
+
+// 本次运行的开始时间；validate_and_fix_index 等没有 AppHandle 的路径也要读取，因此放在全局而不是 AppState
+static SESSION_STARTED_AT: std::sync::OnceLock<DateTime<Local>> = std::sync::OnceLock::new();
+
+// 判断过期时使用的时间点。开启 pauseExpiryWhileRunning 时取 min(now, 会话开始时间)：
+// 启动前就已过期的便签照常归档，启动后才到期的便签在本次运行期间不归档
+// 运行时间超过淡出周期时，这些便签会在下次启动时（按新的会话开始时间）一次性归档
+fn expiry_cutoff(now: &DateTime<Local>, settings: &ScheduleSettings) -> DateTime<Local> {
+    match SESSION_STARTED_AT.get() {
+        Some(started_at) if settings.pause_expiry_while_running => (*now).min(*started_at),
+        _ => *now,
+    }
+}
+
 // RULE: lifecycle mutation only here
 // Fix 3: 新增明确的生命周期阶段 —— expire pass
-fn apply_expire_pass(index: &mut IndexFile, now: &DateTime<Local>) {
+fn apply_expire_pass(notes_dir: &Path, index: &mut IndexFile, now: &DateTime<Local>, settings: &ScheduleSettings) {
+    // 冻结中（包括已到期但还没解冻）不归档；解冻要保存设置，交给 run_lifecycle_pass 处理
+    if fading_freeze_window(settings).is_some() {
+        return;
+    }
+    let cutoff = expiry_cutoff(now, settings);
+    let expired_ids = expired_active_note_ids(index, &cutoff);
+    let discarded_ids = discard_empty_expired_notes(notes_dir, index, &expired_ids, settings);
+    if !discarded_ids.is_empty() {
+        info!(note_ids = ?discarded_ids, "丢弃过期的空白便签");
+    }
+
     for entry in index.notes.iter_mut() {
-        if entry.archived_at.is_none() && is_expired_check(entry, now) {
+        if entry.archived_at.is_none() && is_expired_check(entry, &cutoff) {
             // 调用唯一的归档入口
             if let Err(e) = archive_note(entry, now) {
-                eprintln!("Failed to archive note {}: {}", entry.id, e);
+                error!(note_id = %entry.id, error = %e, "Failed to archive note");
                 // 即使归档失败也标记为已归档，避免重复尝试
                 entry.archived_at = Some(now.to_rfc3339());
             }
@@ -274,6 +718,64 @@ fn apply_expire_pass(index: &mut IndexFile, now: &DateTime<Local>) {
     }
 }
 
+// 开启 discardEmptyOnExpire 时，直接删除过期且正文为空白的便签（文件和索引条目）而不是归档，返回被删除的ID
+// 固定便签永远不会被丢弃
+fn discard_empty_expired_notes(notes_dir: &Path, index: &mut IndexFile, expired_ids: &[String], settings: &ScheduleSettings) -> Vec<String> {
+    if expired_ids.is_empty() || !settings.discard_empty_on_expire {
+        return Vec::new();
+    }
+
+    let mut discarded_ids = Vec::new();
+    for entry in index.notes.iter().filter(|entry| !entry.pinned && expired_ids.contains(&entry.id)) {
+        let file_path = notes_dir.join(&entry.file.relative_path);
+        let is_blank = fs::read_to_string(&file_path)
+            .is_ok_and(|content| extract_content_only(&content).trim().is_empty());
+        if !is_blank {
+            continue;
+        }
+        if let Err(e) = fs::remove_file(&file_path) {
+            error!(note_id = %entry.id, error = %e, "删除空白便签文件失败");
+            continue;
+        }
+        discarded_ids.push(entry.id.clone());
+    }
+
+    index.notes.retain(|entry| !discarded_ids.contains(&entry.id));
+    discarded_ids
+}
+
+// 永久删除归档超过设定天数的便签（文件和索引条目），返回被删除的ID
+// 固定便签和 archivedAt 无法解析的便签不参与清理
+fn purge_archived_notes(notes_dir: &Path, index: &mut IndexFile, now: &DateTime<Local>, settings: &ScheduleSettings) -> Vec<String> {
+    let days = match settings.purge_archived_after_days {
+        Some(days) => days,
+        None => return Vec::new(),
+    };
+    let cutoff = *now - Duration::days(days as i64);
+
+    let mut purged_ids = Vec::new();
+    for entry in index.notes.iter().filter(|entry| !entry.pinned) {
+        let archived_time = match entry.archived_at.as_deref().map(DateTime::parse_from_rfc3339) {
+            Some(Ok(archived_time)) => archived_time,
+            _ => continue,
+        };
+        if archived_time >= cutoff {
+            continue;
+        }
+        let file_path = notes_dir.join(&entry.file.relative_path);
+        if file_path.exists() {
+            if let Err(e) = fs::remove_file(&file_path) {
+                error!(note_id = %entry.id, error = %e, "清理归档便签文件失败");
+                continue;
+            }
+        }
+        purged_ids.push(entry.id.clone());
+    }
+
+    index.notes.retain(|entry| !purged_ids.contains(&entry.id));
+    purged_ids
+}
+
 // Fix 5: 重建索引 - 不得重置生命周期
 fn expired_active_note_ids(index: &IndexFile, now: &DateTime<Local>) -> Vec<String> {
     index.notes.iter()
@@ -286,7 +788,7 @@ fn archive_expired_notes_by_id(index: &mut IndexFile, note_ids: &[String], now:
     for entry in index.notes.iter_mut() {
         if note_ids.iter().any(|id| id == &entry.id) && entry.archived_at.is_none() {
             if let Err(e) = archive_note(entry, now) {
-                eprintln!("Failed to archive note {}: {}", entry.id, e);
+                error!(note_id = %entry.id, error = %e, "Failed to archive note");
                 entry.archived_at = Some(now.to_rfc3339());
                 entry.expire_at = None;
             }
@@ -294,6 +796,68 @@ fn archive_expired_notes_by_id(index: &mut IndexFile, note_ids: &[String], now:
     }
 }
 
+// 活跃便签超出上限时，按 last_active_at 从旧到新归档非固定便签
+// 固定和永不淡出的便签既不计入上限，也不会被淘汰；keep_id 指定的便签（如刚创建的）不会被淘汰
+fn enforce_active_note_cap(index: &mut IndexFile, max_active: usize, keep_id: Option<&str>, now: &DateTime<Local>) -> Vec<String> {
+    let mut candidates: Vec<(Option<DateTime<chrono::FixedOffset>>, String)> = index.notes.iter()
+        .filter(|entry| is_active(entry) && !never_fades(entry))
+        .map(|entry| (DateTime::parse_from_rfc3339(&entry.last_active_at).ok(), entry.id.clone()))
+        .collect();
+    if candidates.len() <= max_active {
+        return Vec::new();
+    }
+
+    let excess = candidates.len() - max_active;
+    // 无法解析的时间视为最旧
+    candidates.sort_by_key(|(last_active, _)| *last_active);
+    let evicted_ids: Vec<String> = candidates.into_iter()
+        .map(|(_, id)| id)
+        .filter(|id| Some(id.as_str()) != keep_id)
+        .take(excess)
+        .collect();
+
+    archive_expired_notes_by_id(index, &evicted_ids, now);
+    for entry in index.notes.iter_mut().filter(|entry| evicted_ids.contains(&entry.id)) {
+        derive_status(entry);
+    }
+    evicted_ids
+}
+
+// 按设置中的活跃便签上限自动归档，并隐藏被归档便签的窗口
+fn apply_active_note_cap(app_handle: &tauri::AppHandle, notes_dir: &Path, keep_id: Option<&str>, settings: &ScheduleSettings) -> Result<Vec<String>, String> {
+    let max_active = match settings.max_active_notes {
+        Some(max_active) => max_active,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut index = read_index(notes_dir)?;
+    let evicted_ids = enforce_active_note_cap(&mut index, max_active, keep_id, &Local::now());
+    if evicted_ids.is_empty() {
+        return Ok(evicted_ids);
+    }
+    save_index(notes_dir, &mut index)?;
+
+    for id in &evicted_ids {
+        if let Some(note_window) = app_handle.get_webview_window(&format!("note-{}", id)) {
+            let _ = note_window.hide();
+        }
+    }
+    let _ = app_handle.emit("fadenote://notes-auto-archived", evicted_ids.clone());
+    emit_index_changed(app_handle, "archived", evicted_ids.clone());
+    Ok(evicted_ids)
+}
+
+// 通知所有窗口（如归档窗口）索引发生了变化
+fn emit_index_changed(app_handle: &tauri::AppHandle, kind: &str, ids: Vec<String>) {
+    if ids.is_empty() {
+        return;
+    }
+    let _ = app_handle.emit("fadenote://index-changed", IndexChange {
+        kind: kind.to_string(),
+        ids,
+    });
+}
+
 fn read_index_or_rebuild(app_data_dir: &Path) -> Result<IndexFile, String> {
     let index_path = app_data_dir.join("index.json");
     if !index_path.exists() {
@@ -302,10 +866,53 @@ fn read_index_or_rebuild(app_data_dir: &Path) -> Result<IndexFile, String> {
 
     let content = fs::read_to_string(&index_path)
         .map_err(|e| format!("read index failed: {}", e))?;
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(&content) {
+        ensure_supported_index_version(&value)?;
+    }
     serde_json::from_str::<IndexFile>(&content)
         .or_else(|_| rebuild_index(app_data_dir))
 }
 
+// 更新版本写入的索引可能含有不认识的字段，按当前结构解析、重建或回写都会丢失数据，
+// 因此在任何类型化解析之前先按原始 JSON 中的 version 拒绝
+fn ensure_supported_index_version(value: &serde_json::Value) -> Result<(), String> {
+    match value.get("version").and_then(serde_json::Value::as_u64) {
+        Some(version) if version > u64::from(INDEX_VERSION) => {
+            Err(format!("索引版本 {} 高于当前支持的版本 {}，请升级 FadeNote", version, INDEX_VERSION))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn read_index(app_data_dir: &Path) -> Result<IndexFile, String> {
+    let index_path = app_data_dir.join("index.json");
+    if !index_path.exists() {
+        return Err("索引文件不存在".to_string());
+    }
+
+    let content = fs::read_to_string(&index_path)
+        .map_err(|e| format!("读取索引文件失败: {}", e))?;
+    parse_index(&content)
+}
+
+// 解析索引内容：先检查版本，高于当前支持版本的索引直接报错
+fn parse_index(content: &str) -> Result<IndexFile, String> {
+    let value: serde_json::Value = serde_json::from_str(content)
+        .map_err(|e| format!("解析索引文件失败: {}", e))?;
+    ensure_supported_index_version(&value)?;
+    serde_json::from_value(value)
+        .map_err(|e| format!("解析索引文件失败: {}", e))
+}
+
+// 索引文件不存在时返回空索引；读取或解析失败仍然报错，避免覆盖已有索引
+fn read_index_or_empty(app_data_dir: &Path) -> Result<IndexFile, String> {
+    if app_data_dir.join("index.json").exists() {
+        read_index(app_data_dir)
+    } else {
+        Ok(new_empty_index())
+    }
+}
+
 fn save_index(app_data_dir: &Path, index: &mut IndexFile) -> Result<(), String> {
     for entry in &mut index.notes {
         derive_status(entry);
@@ -317,11 +924,81 @@ fn save_index(app_data_dir: &Path, index: &mut IndexFile) -> Result<(), String>
         .map_err(|e| format!("write index failed: {}", e))
 }
 
+// 触发到期的提醒：打开活跃便签的窗口并发送 note-reminder 事件
+// 一次性提醒触发后清除；重复提醒顺延到下一个未来的时间点，错过的多次提醒（如启动时）只触发一次
+async fn fire_due_reminders(app_handle: &tauri::AppHandle, notes_dir: &Path, index: &mut IndexFile, now: &DateTime<Local>) -> Result<(), String> {
+    let mut fired_notes = Vec::new();
+    for entry in index.notes.iter_mut().filter(|entry| is_active(entry)) {
+        let reminder_time = match entry.reminder_at.as_deref().map(DateTime::parse_from_rfc3339) {
+            Some(Ok(reminder_time)) => reminder_time,
+            _ => continue,
+        };
+        if reminder_time > *now {
+            continue;
+        }
+        entry.reminder_at = entry.reminder_repeat_hours.filter(|hours| *hours > 0).map(|hours| {
+            let step = Duration::hours(hours as i64);
+            let missed = (*now - reminder_time.with_timezone(&Local)).num_seconds() / step.num_seconds() + 1;
+            (reminder_time + Duration::seconds(step.num_seconds() * missed)).to_rfc3339()
+        });
+        fired_notes.push(entry.clone());
+    }
+    if fired_notes.is_empty() {
+        return Ok(());
+    }
+    save_index(notes_dir, index)?;
+
+    for note in &fired_notes {
+        if let Err(e) = open_note_window(app_handle, note).await {
+            error!(note_id = %note.id, error = %e, "打开提醒便签失败");
+            continue;
+        }
+        let _ = app_handle.emit("fadenote://note-reminder", note.id.clone());
+    }
+    emit_index_changed(app_handle, "updated", fired_notes.into_iter().map(|note| note.id).collect());
+    Ok(())
+}
+
 async fn run_lifecycle_pass(app_handle: tauri::AppHandle) -> Result<(), String> {
     let app_data_dir = get_app_data_dir()?;
     let mut index = read_index_or_rebuild(&app_data_dir)?;
     let now = Local::now();
-    let expired_ids = expired_active_note_ids(&index, &now);
+
+    // 提醒失败不影响后面的解冻、清理和归档
+    if let Err(e) = fire_due_reminders(&app_handle, &app_data_dir, &mut index, &now).await {
+        error!(error = %e, "触发到期提醒失败");
+    }
+
+    let lapsed_ids = take_temporary_pins(&mut app_handle.state::<AppState>().temporary_pins.lock().unwrap(), Some(&now));
+    let unpinned_ids = unpin_entries(&mut index, &lapsed_ids);
+    if !unpinned_ids.is_empty() {
+        save_index(&app_data_dir, &mut index)?;
+        emit_index_changed(&app_handle, "updated", unpinned_ids);
+    }
+
+    // 冻结期间不清理、不归档；冻结已到期时先解冻，保存顺延后的过期时间再继续
+    let mut settings = load_schedule_settings_from_disk();
+    let had_freeze = fading_freeze_window(&settings).is_some();
+    let shifted_ids = match thaw_lapsed_freeze(&mut index, &mut settings, &now) {
+        Some(shifted_ids) => shifted_ids,
+        None => return Ok(()),
+    };
+    if had_freeze {
+        if !shifted_ids.is_empty() {
+            save_index(&app_data_dir, &mut index)?;
+        }
+        save_schedule_settings_to_disk(&settings)?;
+        emit_index_changed(&app_handle, "updated", shifted_ids);
+    }
+
+    let purged_ids = purge_archived_notes(&app_data_dir, &mut index, &now, &settings);
+    if !purged_ids.is_empty() {
+        save_index(&app_data_dir, &mut index)?;
+        let _ = app_handle.emit("fadenote://note-deleted", purged_ids.clone());
+        emit_index_changed(&app_handle, "deleted", purged_ids);
+    }
+
+    let expired_ids = expired_active_note_ids(&index, &expiry_cutoff(&now, &settings));
 
     if expired_ids.is_empty() {
         return Ok(());
@@ -337,7 +1014,7 @@ async fn run_lifecycle_pass(app_handle: tauri::AppHandle) -> Result<(), String>
     std::thread::sleep(StdDuration::from_millis(650));
 
     index = read_index_or_rebuild(&app_data_dir)?;
-    let still_expired_ids: Vec<String> = expired_active_note_ids(&index, &Local::now())
+    let still_expired_ids: Vec<String> = expired_active_note_ids(&index, &expiry_cutoff(&Local::now(), &settings))
         .into_iter()
         .filter(|id| expired_ids.iter().any(|expired_id| expired_id == id))
         .collect();
@@ -349,8 +1026,19 @@ async fn run_lifecycle_pass(app_handle: tauri::AppHandle) -> Result<(), String>
         }
     }
 
-    archive_expired_notes_by_id(&mut index, &still_expired_ids, &Local::now());
-    save_index(&app_data_dir, &mut index)
+    let discarded_ids = discard_empty_expired_notes(&app_data_dir, &mut index, &still_expired_ids, &settings);
+    let archived_ids: Vec<String> = still_expired_ids.into_iter()
+        .filter(|id| !discarded_ids.contains(id))
+        .collect();
+
+    archive_expired_notes_by_id(&mut index, &archived_ids, &Local::now());
+    save_index(&app_data_dir, &mut index)?;
+    if !discarded_ids.is_empty() {
+        let _ = app_handle.emit("fadenote://note-deleted", discarded_ids.clone());
+        emit_index_changed(&app_handle, "deleted", discarded_ids);
+    }
+    emit_index_changed(&app_handle, "archived", archived_ids);
+    Ok(())
 }
 
 fn rebuild_index(notes_dir: &Path) -> Result<IndexFile, String> {
@@ -360,6 +1048,9 @@ fn rebuild_index(notes_dir: &Path) -> Result<IndexFile, String> {
     let mut existing_entries_map: std::collections::HashMap<String, NoteEntry> = std::collections::HashMap::new();
     let old_index: Option<IndexFile> = if index_path.exists() {
         if let Ok(content) = fs::read_to_string(&index_path) {
+            if let Ok(value) = serde_json::from_str::<serde_json::Value>(&content) {
+                ensure_supported_index_version(&value)?;
+            }
             if let Ok(existing_index) = serde_json::from_str::<IndexFile>(&content) {
                 for entry in &existing_index.notes {
                     existing_entries_map.insert(entry.id.clone(), entry.clone());
@@ -382,7 +1073,7 @@ fn rebuild_index(notes_dir: &Path) -> Result<IndexFile, String> {
         .unwrap_or_else(get_current_iso8601_time);
     
     let mut index = IndexFile {
-        version: 2,
+        version: INDEX_VERSION,
         app: AppInfo {
             name: "FadeNote".to_string(),
             created_at: app_created_at,
@@ -394,7 +1085,8 @@ fn rebuild_index(notes_dir: &Path) -> Result<IndexFile, String> {
     // 扫描notes目录下的所有文件并添加到索引中
     let notes_path = notes_dir.join("notes");
     if notes_path.exists() {
-        scan_directory_for_notes_rebuild(notes_dir, &mut index, &notes_path, &existing_entries_map)?;
+        let settings = load_schedule_settings_from_disk();
+        scan_directory_for_notes_rebuild(notes_dir, &mut index, &notes_path, &existing_entries_map, &settings)?;
     }
 
     // 派生所有条目的状态
@@ -412,7 +1104,7 @@ fn rebuild_index(notes_dir: &Path) -> Result<IndexFile, String> {
 }
 
 // 扫描目录中的便签文件用于重建 - 递归辅助函数
-fn scan_directory_for_notes_rebuild_recursive(notes_dir: &Path, index: &mut IndexFile, scan_path: &Path, existing_entries: &std::collections::HashMap<String, NoteEntry>) -> Result<(), String> {
+fn scan_directory_for_notes_rebuild_recursive(notes_dir: &Path, index: &mut IndexFile, scan_path: &Path, existing_entries: &std::collections::HashMap<String, NoteEntry>, settings: &ScheduleSettings) -> Result<(), String> {
     for entry in fs::read_dir(scan_path).map_err(|e| format!("读取目录失败: {}", e))? {
         let entry = entry.map_err(|e| format!("遍历文件失败: {}", e))?;
         let path = entry.path();
@@ -422,8 +1114,7 @@ fn scan_directory_for_notes_rebuild_recursive(notes_dir: &Path, index: &mut Inde
             if let Ok(content) = fs::read_to_string(&path) {
                 if let Some(parsed_id) = parse_id_from_content(&content) {
                     let metadata = path.metadata().map_err(|e| format!("获取文件元数据失败: {}", e))?;
-                    let created_time = DateTime::<Utc>::from(metadata.created()
-                        .map_err(|e| format!("获取创建时间失败: {}", e))?);
+                    let created_time = file_created_time(metadata.created(), metadata.modified(), Utc::now());
                     
                     let relative_path = path.strip_prefix(notes_dir)
                         .unwrap_or(&path)
@@ -447,29 +1138,30 @@ fn scan_directory_for_notes_rebuild_recursive(notes_dir: &Path, index: &mut Inde
                         )
                     };
                     
-                    let new_entry = NoteEntry {
-                        id: parsed_id.clone(),
-                        created_at,
+                    // 加密标记和口令哈希必须沿用现有条目，否则重建后加密便签无法再解密
+                    let body = extract_content_only(&content);
+                    let (encryption, protected) = scanned_encryption(&body, existing_entries.get(&parsed_id), settings);
+
+                    let mut new_entry = NoteEntry {
                         last_active_at,
                         expire_at,
-                        cached_preview: None,
-                        status: String::new(), // 禁止手写，将在派生时设置
+                        cached_preview: if encryption == BodyEncryption::None { note_preview(&body, settings) } else { None },
                         archived_at,
-                        window: None,    // 重建时所有window都是null
-                        pinned: false,  // 默认不固定
-                        file: FileInfo {
-                            relative_path,
-                        },
+                        protected,
+                        encryption,
+                        // 重建时所有window都是null
+                        ..NoteEntry::new(parsed_id.clone(), created_at, relative_path, None)
                     };
-                    
+                    derive_status(&mut new_entry);
+
                     // 重建索引时应该保留所有note，无论是否活跃
                     index.notes.push(new_entry);
-                    println!("重建时添加note到索引: {}", parsed_id);
+                    debug!(note_id = %parsed_id, "重建时添加note到索引");
                 }
             }
         } else if path.is_dir() {
             // 递归扫描子目录
-            scan_directory_for_notes_rebuild_recursive(notes_dir, index, &path, existing_entries)?;
+            scan_directory_for_notes_rebuild_recursive(notes_dir, index, &path, existing_entries, settings)?;
         }
     }
     
@@ -477,8 +1169,32 @@ fn scan_directory_for_notes_rebuild_recursive(notes_dir: &Path, index: &mut Inde
 }
 
 // 扫描目录中的便签文件用于重建
-fn scan_directory_for_notes_rebuild(notes_dir: &Path, index: &mut IndexFile, scan_path: &Path, existing_entries: &std::collections::HashMap<String, NoteEntry>) -> Result<(), String> {
-    scan_directory_for_notes_rebuild_recursive(notes_dir, index, scan_path, existing_entries)
+fn scan_directory_for_notes_rebuild(notes_dir: &Path, index: &mut IndexFile, scan_path: &Path, existing_entries: &std::collections::HashMap<String, NoteEntry>, settings: &ScheduleSettings) -> Result<(), String> {
+    scan_directory_for_notes_rebuild_recursive(notes_dir, index, scan_path, existing_entries, settings)
+}
+
+// 对比检查前后的索引：before 为 None 表示原索引缺失或无法解析（已重建）
+fn build_startup_report(notes_dir: &Path, before: Option<&IndexFile>, after: &IndexFile) -> StartupReport {
+    let adopted = match before {
+        Some(before) => {
+            let known_ids: std::collections::HashSet<&str> = before.notes.iter().map(|note| note.id.as_str()).collect();
+            after.notes.iter()
+                .filter(|note| !known_ids.contains(note.id.as_str()))
+                .map(|note| note.id.clone())
+                .collect()
+        }
+        None => Vec::new(),
+    };
+    let missing_files = after.notes.iter()
+        .filter(|note| !notes_dir.join(&note.file.relative_path).is_file())
+        .map(|note| note.id.clone())
+        .collect();
+
+    StartupReport {
+        rebuilt: before.is_none(),
+        adopted,
+        missing_files,
+    }
 }
 
 // 规范化索引 - 修正非法状态
@@ -490,85 +1206,314 @@ fn normalize_index(mut index: IndexFile) -> IndexFile {
     // window=null 的 note 不创建窗口
     // 在窗口恢复逻辑中已经处理了这一点
     
-    // 修正非法字段值
+    // 修正非法字段值：检查规则与 check_index 共用 entry_fixes
     for entry in &mut index.notes {
-        // 确保ID有效
-        if entry.id.is_empty() {
-            entry.id = Uuid::new_v4().to_string();
-        }
-        
-        // 确保时间字段格式正确
-        if entry.created_at.is_empty() {
-            entry.created_at = get_current_iso8601_time();
-        }
-        
-        if entry.last_active_at.is_empty() {
-            entry.last_active_at = get_current_iso8601_time();
+        for fix in entry_fixes(entry) {
+            apply_entry_fix(entry, fix);
         }
+    }
+    
+    index
+}
+
+// normalize_index 能自动修正的条目问题
+#[derive(Clone, Copy)]
+enum EntryFix {
+    EmptyId,
+    EmptyCreatedAt,
+    EmptyLastActiveAt,
+    MissingExpireAt,
+    WindowTooSmall,
+    EmptyFilePath,
+}
+
+// 条目上需要修正的问题，按修正顺序排列（文件路径依赖修正后的ID，所以 EmptyId 在前）
+// normalize_index 逐个修正，check_index 只报告，两边的判断只写在这里
+fn entry_fixes(entry: &NoteEntry) -> Vec<EntryFix> {
+    let mut fixes = Vec::new();
+    if entry.id.is_empty() {
+        fixes.push(EntryFix::EmptyId);
+    }
+    if entry.created_at.is_empty() {
+        fixes.push(EntryFix::EmptyCreatedAt);
+    }
+    if entry.last_active_at.is_empty() {
+        fixes.push(EntryFix::EmptyLastActiveAt);
+    }
+    if entry.archived_at.is_none() && !entry.pinned && entry.expire_at.is_none() {
+        fixes.push(EntryFix::MissingExpireAt);
+    }
+    if entry.window.as_ref().is_some_and(|window_info| window_info.width < NOTE_MIN_WIDTH || window_info.height < NOTE_MIN_HEIGHT) {
+        fixes.push(EntryFix::WindowTooSmall);
+    }
+    if entry.file.relative_path.is_empty() {
+        fixes.push(EntryFix::EmptyFilePath);
+    }
+    fixes
+}
 
-        if entry.archived_at.is_none() && !entry.pinned && entry.expire_at.is_none() {
+fn apply_entry_fix(entry: &mut NoteEntry, fix: EntryFix) {
+    match fix {
+        EntryFix::EmptyId => entry.id = Uuid::new_v4().to_string(),
+        EntryFix::EmptyCreatedAt => entry.created_at = get_current_iso8601_time(),
+        EntryFix::EmptyLastActiveAt => entry.last_active_at = get_current_iso8601_time(),
+        EntryFix::MissingExpireAt => {
             let base_time = DateTime::parse_from_rfc3339(&entry.last_active_at)
                 .map(|time| time.with_timezone(&Local))
                 .unwrap_or_else(|_| Local::now());
             entry.expire_at = Some((base_time + Duration::days(7)).to_rfc3339());
         }
-        
-        // 确保文件路径有效
-        if entry.file.relative_path.is_empty() {
-            entry.file.relative_path = format!("notes/unknown/{}.md", entry.id);
+        // 窗口尺寸低于最小值时抬升到最小值
+        EntryFix::WindowTooSmall => {
+            if let Some(window_info) = entry.window.as_mut() {
+                window_info.width = window_info.width.max(NOTE_MIN_WIDTH);
+                window_info.height = window_info.height.max(NOTE_MIN_HEIGHT);
+            }
         }
-        
-        // 确保pinned字段存在（默认为false）
-        // 注意：这里不需要设置默认值，因为我们已经在创建NoteEntry时设置了
+        EntryFix::EmptyFilePath => entry.file.relative_path = format!("notes/unknown/{}.md", entry.id),
     }
-    
-    index
 }
 
-// 验证并修复索引
-fn validate_and_fix_index(notes_dir: &Path) -> Result<IndexFile, String> {
-    let index_path = notes_dir.join("index.json");
-    let mut index: IndexFile = if index_path.exists() {
-        let content = fs::read_to_string(&index_path)
-            .map_err(|e| format!("读取索引文件失败: {}", e))?;
-        match serde_json::from_str::<IndexFile>(&content) {
-            Ok(parsed_index) => parsed_index,
-            Err(_) => {
-                // 如果解析失败，执行重建
-                println!("索引文件解析失败，执行重建...");
-                return rebuild_index(notes_dir);
-            }
+// 问题类型和说明
+fn entry_fix_issue(entry: &NoteEntry, fix: EntryFix) -> (IndexProblem, String) {
+    match fix {
+        EntryFix::EmptyId => (IndexProblem::EmptyId, "ID 为空".to_string()),
+        EntryFix::EmptyCreatedAt => (IndexProblem::EmptyCreatedAt, "createdAt 为空".to_string()),
+        EntryFix::EmptyLastActiveAt => (IndexProblem::EmptyLastActiveAt, "lastActiveAt 为空".to_string()),
+        EntryFix::MissingExpireAt => (IndexProblem::MissingExpireAt, "活跃便签缺少 expireAt".to_string()),
+        EntryFix::WindowTooSmall => {
+            let (width, height) = entry.window.as_ref().map(|window_info| (window_info.width, window_info.height)).unwrap_or_default();
+            (IndexProblem::WindowTooSmall, format!("窗口尺寸 {}x{} 小于最小值", width, height))
         }
-    } else {
-        // 如果不存在，执行重建
-        println!("索引文件不存在，执行重建...");
-        return rebuild_index(notes_dir);
-    };
-
-    // 保留原有的rebuildAt值，不进行修改（V2规范：普通启动/更新禁止写入rebuildAt）
-    let original_rebuild_at = index.app.rebuild_at.clone();
+        EntryFix::EmptyFilePath => (IndexProblem::EmptyFilePath, "文件路径为空".to_string()),
+    }
+}
 
-    // 不再检查文件是否存在，保留所有条目
-    // 文件不会被移动，所以不需要检查文件是否存在
+// 只读地列出 normalize_index 会修正的问题（entry_fixes），另外报告它不处理的重复ID、无法解析的时间和与 archivedAt 不符的 status
+fn index_issues(index: &IndexFile) -> Vec<IndexIssue> {
+    let mut issues = Vec::new();
+    let mut push = |entry: &NoteEntry, kind: IndexProblem, detail: String| issues.push(IndexIssue {
+        id: entry.id.clone(),
+        kind,
+        detail,
+    });
 
-    // 扫描notes目录下的所有文件，仅添加当前索引中不存在的文件
-    // 避免重复添加已存在的便签
-    let current_index_ids: std::collections::HashSet<String> = index.notes.iter().map(|note| note.id.clone()).collect();
+    let mut seen_ids = std::collections::HashSet::new();
+    for entry in &index.notes {
+        for fix in entry_fixes(entry) {
+            let (kind, detail) = entry_fix_issue(entry, fix);
+            push(entry, kind, detail);
+        }
+        if !entry.id.is_empty() && !seen_ids.insert(entry.id.as_str()) {
+            push(entry, IndexProblem::DuplicateId, "ID 重复".to_string());
+        }
+        let timestamps = [
+            ("createdAt", Some(entry.created_at.as_str())),
+            ("lastActiveAt", Some(entry.last_active_at.as_str())),
+            ("expireAt", entry.expire_at.as_deref()),
+            ("archivedAt", entry.archived_at.as_deref()),
+        ];
+        for (field, value) in timestamps {
+            if let Some(value) = value.filter(|value| !value.is_empty()) {
+                if DateTime::parse_from_rfc3339(value).is_err() {
+                    push(entry, IndexProblem::UnparseableTimestamp, format!("{} 无法解析: {}", field, value));
+                }
+            }
+        }
+        let expected_status = if entry.archived_at.is_some() { "archived" } else { "active" };
+        if entry.status != expected_status {
+            push(entry, IndexProblem::StatusMismatch, format!("status 为 \"{}\"，应为 \"{}\"", entry.status, expected_status));
+        }
+    }
+    issues
+}
+
+// 检查 index.json 中的问题，另外报告文件缺失的条目和未收录到索引的孤立文件；只读，不写入索引
+#[tauri::command]
+async fn check_index(window: tauri::WebviewWindow) -> Result<Vec<IndexIssue>, String> {
+    let app_data_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&app_data_dir)?;
+    let mut issues = index_issues(&index);
+
+    for entry in &index.notes {
+        if !entry.file.relative_path.is_empty() && !app_data_dir.join(&entry.file.relative_path).is_file() {
+            issues.push(IndexIssue {
+                id: entry.id.clone(),
+                kind: IndexProblem::MissingFile,
+                detail: entry.file.relative_path.clone(),
+            });
+        }
+    }
+
+    let indexed_ids: std::collections::HashSet<&str> = index.notes.iter().map(|entry| entry.id.as_str()).collect();
+    for path in collect_markdown_files(&app_data_dir.join("notes")) {
+        let parsed_id = fs::read_to_string(&path).ok().and_then(|content| parse_id_from_content(&content));
+        if parsed_id.as_deref().is_some_and(|id| indexed_ids.contains(id)) {
+            continue;
+        }
+        issues.push(IndexIssue {
+            id: parsed_id.unwrap_or_default(),
+            kind: IndexProblem::OrphanFile,
+            detail: path.strip_prefix(&app_data_dir)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .to_string(),
+        });
+    }
+    Ok(issues)
+}
+
+// V1 索引 -> V2：V1 没有 version 字段，键名为 snake_case，文件路径直接放在条目的 path / relative_path 上
+// 缺失的时间用 createdAt 或当前时间补齐，无法转换的条目丢弃（文件会在随后的目录扫描中重新加入）
+// 迁移相当于重写了索引，rebuildAt 记为迁移时间
+fn migrate_index_v1_to_v2(value: serde_json::Value) -> IndexFile {
+    use serde_json::{json, Map, Value};
+
+    const RENAMED_KEYS: [(&str, &str); 8] = [
+        ("created_at", "createdAt"),
+        ("last_active_at", "lastActiveAt"),
+        ("expire_at", "expireAt"),
+        ("archived_at", "archivedAt"),
+        ("cached_preview", "cachedPreview"),
+        ("rebuild_at", "rebuildAt"),
+        ("font_size", "fontSize"),
+        ("relative_path", "relativePath"),
+    ];
+    let rename_keys = |object: &mut Map<String, Value>| {
+        for (old_key, new_key) in RENAMED_KEYS {
+            if let Some(value) = object.remove(old_key) {
+                object.entry(new_key).or_insert(value);
+            }
+        }
+    };
+
+    let now = get_current_iso8601_time();
+    let mut app = value.get("app").and_then(Value::as_object).cloned().unwrap_or_default();
+    rename_keys(&mut app);
+    let created_at = app.get("createdAt").and_then(Value::as_str).map(str::to_string).unwrap_or_else(|| now.clone());
+
+    let notes = value.get("notes").and_then(Value::as_array).cloned().unwrap_or_default()
+        .into_iter()
+        .filter_map(|note| {
+            let mut note = note.as_object()?.clone();
+            rename_keys(&mut note);
+            if !note.contains_key("file") {
+                let relative_path = note.remove("relativePath").or_else(|| note.remove("path"))?;
+                note.insert("file".to_string(), json!({ "relativePath": relative_path }));
+            }
+            let created_at = note.get("createdAt").cloned().unwrap_or_else(|| json!(now));
+            note.entry("createdAt").or_insert(created_at.clone());
+            note.entry("lastActiveAt").or_insert(created_at);
+            note.entry("pinned").or_insert(json!(false));
+            note.entry("status").or_insert(json!(""));
+            match serde_json::from_value::<NoteEntry>(Value::Object(note)) {
+                Ok(mut entry) => {
+                    derive_status(&mut entry);
+                    Some(entry)
+                }
+                Err(e) => {
+                    warn!(error = %e, "V1 索引条目无法迁移，已跳过");
+                    None
+                }
+            }
+        })
+        .collect();
+
+    IndexFile {
+        version: INDEX_VERSION,
+        app: AppInfo {
+            name: "FadeNote".to_string(),
+            created_at,
+            rebuild_at: Some(now),
+        },
+        notes,
+    }
+}
+
+// 验证并修复索引
+fn validate_and_fix_index(notes_dir: &Path) -> Result<IndexFile, String> {
+    let settings = load_schedule_settings_from_disk();
+    let index_path = notes_dir.join("index.json");
+    let mut index: IndexFile = if index_path.exists() {
+        let content = fs::read_to_string(&index_path)
+            .map_err(|e| format!("读取索引文件失败: {}", e))?;
+        let value = serde_json::from_str::<serde_json::Value>(&content).ok();
+        if let Some(value) = value.as_ref() {
+            ensure_supported_index_version(value)?;
+        }
+        match value.filter(|value| value.get("version").and_then(serde_json::Value::as_u64).unwrap_or(1) < u64::from(INDEX_VERSION)) {
+            // V1 索引就地迁移，保留窗口位置和固定状态；迁移前留一份原文件
+            Some(value) => {
+                info!("检测到 V1 索引，迁移到 V2...");
+                if let Err(e) = fs::copy(&index_path, notes_dir.join("index.v1.json")) {
+                    warn!(error = %e, "备份 V1 索引失败");
+                }
+                migrate_index_v1_to_v2(value)
+            }
+            None => match serde_json::from_str::<IndexFile>(&content) {
+                Ok(parsed_index) => parsed_index,
+                Err(_) => {
+                    // 如果解析失败，执行重建
+                    warn!("索引文件解析失败，执行重建...");
+                    return rebuild_index(notes_dir);
+                }
+            },
+        }
+    } else {
+        // 如果不存在，执行重建
+        info!("索引文件不存在，执行重建...");
+        return rebuild_index(notes_dir);
+    };
+
+    // 保留原有的rebuildAt值，不进行修改（V2规范：普通启动/更新禁止写入rebuildAt）
+    let original_rebuild_at = index.app.rebuild_at.clone();
+
+    // 不再检查文件是否存在，保留所有条目
+    // 文件不会被移动，所以不需要检查文件是否存在
+
+    // 扫描notes目录下的所有文件，仅添加当前索引中不存在的文件
+    // 避免重复添加已存在的便签
+    let current_index_ids: std::collections::HashSet<String> = index.notes.iter().map(|note| note.id.clone()).collect();
     
     let notes_path = notes_dir.join("notes");
     if notes_path.exists() {
-        scan_directory_for_notes(notes_dir, &mut index, &notes_path, &current_index_ids)?;
+        scan_directory_for_notes(notes_dir, &mut index, &notes_path, &current_index_ids, &settings)?;
     }
 
 
 
     // 应用过期检查
     let now = Local::now();
-    apply_expire_pass(&mut index, &now);
+    apply_expire_pass(notes_dir, &mut index, &now, &settings);
+
+    // 清理归档超期的便签
+    let purged_ids = purge_archived_notes(notes_dir, &mut index, &now, &settings);
+    if !purged_ids.is_empty() {
+        info!(note_ids = ?purged_ids, "清理归档超期的便签");
+    }
     
     // 应用规范化规则
     index = normalize_index(index);
 
+    // 旧索引没有 encryption 标记：只有受保护或开启了全局加密时才可能是密文，按文件补齐标记
+    let global_salt = global_encryption_salt(&settings);
+    for entry in index.notes.iter_mut().filter(|entry| !is_encrypted(entry) && (entry.protected.is_some() || global_salt.is_some())) {
+        if let Ok(content) = fs::read_to_string(notes_dir.join(&entry.file.relative_path)) {
+            entry.encryption = detect_legacy_encryption(&extract_content_only(&content), entry.protected.is_some(), global_salt.as_deref());
+        }
+    }
+    // 旧版本可能为受保护的便签缓存过预览，一并清除
+    for entry in index.notes.iter_mut().filter(|entry| hides_body(entry)) {
+        entry.cached_preview = None;
+    }
+
+    // 为缺少预览的条目从文件补全 cachedPreview
+    for entry in index.notes.iter_mut().filter(|entry| entry.cached_preview.is_none() && !hides_body(entry)) {
+        if let Ok(content) = fs::read_to_string(notes_dir.join(&entry.file.relative_path)) {
+            entry.cached_preview = note_preview(&extract_content_only(&content), &settings);
+        }
+    }
+
     // 恢复原始的rebuildAt值，确保不会在普通更新时修改它
     index.app.rebuild_at = original_rebuild_at;
 
@@ -587,7 +1532,7 @@ fn validate_and_fix_index(notes_dir: &Path) -> Result<IndexFile, String> {
 }
 
 // 扫描目录中的便签文件 - 递归辅助函数
-fn scan_directory_for_notes_recursive(notes_dir: &Path, index: &mut IndexFile, scan_path: &Path, existing_ids: &mut std::collections::HashSet<String>) -> Result<(), String> {
+fn scan_directory_for_notes_recursive(notes_dir: &Path, index: &mut IndexFile, scan_path: &Path, existing_ids: &mut std::collections::HashSet<String>, settings: &ScheduleSettings) -> Result<(), String> {
     // 加载现有的索引以保留状态信息
     let index_path = notes_dir.join("index.json");
     let mut existing_entries_map: std::collections::HashMap<String, NoteEntry> = std::collections::HashMap::new();
@@ -601,7 +1546,7 @@ fn scan_directory_for_notes_recursive(notes_dir: &Path, index: &mut IndexFile, s
         }
     }
     
-    scan_directory_for_notes_recursive_with_existing(notes_dir, index, scan_path, existing_ids, &existing_entries_map)
+    scan_directory_for_notes_recursive_with_existing(notes_dir, index, scan_path, existing_ids, &existing_entries_map, settings)
 }
 
 // 扫描目录中的便签文件 - 递归辅助函数（实际实现）
@@ -610,7 +1555,8 @@ fn scan_directory_for_notes_recursive_with_existing(
     index: &mut IndexFile, 
     scan_path: &Path, 
     existing_ids: &mut std::collections::HashSet<String>,
-    existing_entries: &std::collections::HashMap<String, NoteEntry>
+    existing_entries: &std::collections::HashMap<String, NoteEntry>,
+    settings: &ScheduleSettings
 ) -> Result<(), String> {
     for entry in fs::read_dir(scan_path).map_err(|e| format!("读取目录失败: {}", e))? {
         let entry = entry.map_err(|e| format!("遍历文件失败: {}", e))?;
@@ -623,8 +1569,7 @@ fn scan_directory_for_notes_recursive_with_existing(
                     // 检查这个ID是否已在索引中，如果不在则添加
                     if !existing_ids.contains(&parsed_id) {
                         let metadata = path.metadata().map_err(|e| format!("获取文件元数据失败: {}", e))?;
-                        let created_time = DateTime::<Utc>::from(metadata.created()
-                            .map_err(|e| format!("获取创建时间失败: {}", e))?);
+                        let created_time = file_created_time(metadata.created(), metadata.modified(), Utc::now());
                         
                         let relative_path = path.strip_prefix(notes_dir)
                             .unwrap_or(&path)
@@ -639,36 +1584,37 @@ fn scan_directory_for_notes_recursive_with_existing(
                             (None, None)
                         };
                         
-                        let new_entry = NoteEntry {
-                            id: parsed_id.clone(), // 修复：clone值以避免移动
-                            created_at: created_time.to_rfc3339(),
-                            last_active_at: created_time.to_rfc3339(),
+                        let body = extract_content_only(&content);
+                        let (encryption, protected) = scanned_encryption(&body, existing_entries.get(&parsed_id), settings);
+
+                        let mut new_entry = NoteEntry {
                             expire_at,
-                            cached_preview: None,
-                            status: String::new(), // 禁止手写，将在派生时设置
+                            cached_preview: if encryption == BodyEncryption::None { note_preview(&body, settings) } else { None },
                             archived_at,
-                            window: Some(WindowInfo {
+                            protected,
+                            encryption,
+                            ..NoteEntry::new(parsed_id.clone(), created_time.to_rfc3339(), relative_path, Some(WindowInfo {
                                 x: 100.0,
                                 y: 100.0,
                                 width: 280.0,
                                 height: 360.0,
-                            }),
-                            pinned: false,  // 默认不固定
-                            file: FileInfo {
-                                relative_path,
-                            },
+                                decorated: false,
+                                on_all_desktops: false,
+                                monitor_index: None,
+                            }))
                         };
-                        
+                        derive_status(&mut new_entry);
+
                         // 添加note到索引中（扫描时保留所有note，不管是否活跃）
                         index.notes.push(new_entry);
                         existing_ids.insert(parsed_id.clone()); // 添加到已知ID集合
-                        println!("添加新发现的note到索引: {}", parsed_id); // 修复：使用克隆的值
+                        info!(note_id = %parsed_id, "添加新发现的note到索引"); // 修复：使用克隆的值
                     }
                 }
             }
         } else if path.is_dir() {
             // 递归扫描子目录
-            scan_directory_for_notes_recursive_with_existing(notes_dir, index, &path, existing_ids, existing_entries)?;
+            scan_directory_for_notes_recursive_with_existing(notes_dir, index, &path, existing_ids, existing_entries, settings)?;
         }
     }
     
@@ -676,11 +1622,11 @@ fn scan_directory_for_notes_recursive_with_existing(
 }
 
 // 扫描目录中的便签文件
-fn scan_directory_for_notes(notes_dir: &Path, index: &mut IndexFile, scan_path: &Path, current_index_ids: &std::collections::HashSet<String>) -> Result<(), String> {
+fn scan_directory_for_notes(notes_dir: &Path, index: &mut IndexFile, scan_path: &Path, current_index_ids: &std::collections::HashSet<String>, settings: &ScheduleSettings) -> Result<(), String> {
     // 使用传入的当前索引ID集合，避免重复添加
     let mut existing_ids = current_index_ids.clone();
 
-    scan_directory_for_notes_recursive(notes_dir, index, scan_path, &mut existing_ids)
+    scan_directory_for_notes_recursive(notes_dir, index, scan_path, &mut existing_ids, settings)
 }
 
 // 初始化便签目录结构
@@ -761,44 +1707,207 @@ async fn get_archived_notes(window: tauri::WebviewWindow) -> Result<Vec<NoteEntr
     Ok(archived_notes)
 }
 
+// 归档便签的 (id, preview, archivedAt)，只读索引，按 archivedAt 倒序（无法解析的排在最后）
+#[tauri::command]
+async fn get_archived_previews(window: tauri::WebviewWindow) -> Result<Vec<(String, Option<String>, String)>, String> {
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+
+    let mut previews: Vec<(String, Option<String>, String)> = index.notes.into_iter()
+        .filter_map(|entry| {
+            let archived_at = entry.archived_at.clone()?;
+            let preview = entry.cached_preview.clone().filter(|_| !hides_body(&entry));
+            Some((entry.id, preview, archived_at))
+        })
+        .collect();
+    previews.sort_by_key(|(_, _, archived_at)| std::cmp::Reverse(DateTime::parse_from_rfc3339(archived_at).ok()));
+    Ok(previews)
+}
+
+// 归档表格使用的行数据，按 archivedAt 倒序（无法解析的排在最后），每个文件只读取一次
+#[tauri::command]
+async fn get_archive_rows(window: tauri::WebviewWindow) -> Result<Vec<ArchiveRow>, String> {
+    let settings = load_schedule_settings_from_disk();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+
+    let date_part = |time: &str| {
+        DateTime::parse_from_rfc3339(time)
+            .map(|time| time.with_timezone(&Local).format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|_| time.chars().take(10).collect())
+    };
+
+    let mut archived: Vec<&NoteEntry> = index.notes.iter().filter(|entry| !is_active(entry)).collect();
+    archived.sort_by_key(|entry| {
+        std::cmp::Reverse(entry.archived_at.as_deref().and_then(|time| DateTime::parse_from_rfc3339(time).ok()))
+    });
+
+    Ok(archived.into_iter()
+        .map(|entry| {
+            let body = fs::read_to_string(notes_dir.join(&entry.file.relative_path))
+                .ok()
+                .map(|content| extract_content_only(&content));
+            let (title_or_preview, word_count) = match &body {
+                Some(body) if !hides_body(entry) => {
+                    (note_preview(body, &settings).or_else(|| entry.cached_preview.clone()), word_count(body))
+                }
+                _ if hides_body(entry) => (None, 0),
+                _ => (entry.cached_preview.clone(), 0),
+            };
+            ArchiveRow {
+                id: entry.id.clone(),
+                title_or_preview,
+                created_date: date_part(&entry.created_at),
+                archived_date: entry.archived_at.as_deref().map(date_part),
+                word_count,
+                pinned: entry.pinned,
+                missing: body.is_none(),
+            }
+        })
+        .collect())
+}
+
+// 按日期目录统计便签文件数和其中的活跃便签数，供日历/热力图使用
+// 只统计日期目录和直接放在 notes/ 下的文件，.history / .attachments 等目录忽略；遍历中消失的文件直接跳过
+#[tauri::command]
+async fn get_folder_summary(window: tauri::WebviewWindow) -> Result<Vec<FolderSummary>, String> {
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+    let active_paths: std::collections::HashSet<PathBuf> = index.notes.iter()
+        .filter(|entry| is_active(entry))
+        .map(|entry| notes_dir.join(&entry.file.relative_path))
+        .collect();
+
+    let count_folder = |folder: String, dir: &Path| {
+        let md_files: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(entries) => entries.flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "md"))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        FolderSummary {
+            folder,
+            md_count: md_files.len(),
+            active_count: md_files.iter().filter(|path| active_paths.contains(*path)).count(),
+        }
+    };
+
+    let notes_subdir = notes_dir.join("notes");
+    let mut summaries = Vec::new();
+    let root_summary = count_folder(String::new(), &notes_subdir);
+    if root_summary.md_count > 0 {
+        summaries.push(root_summary);
+    }
+    if let Ok(entries) = fs::read_dir(&notes_subdir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if path.is_dir() && is_dated_folder_name(&name) {
+                summaries.push(count_folder(name, &path));
+            }
+        }
+    }
+    summaries.sort_by(|a, b| a.folder.cmp(&b.folder));
+    Ok(summaries)
+}
+
+// 按 relativePath 中的日期目录（notes/2024-05-01/... 或 notes/2024-05/...）统计活跃 / 归档便签数
+// 只读索引不扫描磁盘；日期倒序，"unknown" 排在最后
+#[tauri::command]
+async fn get_notes_by_date(window: tauri::WebviewWindow) -> Result<Vec<DateBucket>, String> {
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+
+    let mut buckets: std::collections::BTreeMap<String, (u32, u32)> = std::collections::BTreeMap::new();
+    for entry in &index.notes {
+        let segments: Vec<&str> = entry.file.relative_path.split(['/', '\\']).collect();
+        let date = match segments.as_slice() {
+            ["notes", folder, _, ..] if is_dated_folder_name(folder) => folder.to_string(),
+            _ => "unknown".to_string(),
+        };
+        let counts = buckets.entry(date).or_default();
+        if is_active(entry) {
+            counts.0 += 1;
+        } else {
+            counts.1 += 1;
+        }
+    }
+
+    let unknown = buckets.remove("unknown");
+    let mut date_buckets: Vec<DateBucket> = buckets.into_iter()
+        .rev()
+        .map(|(date, (active, archived))| DateBucket { date, active, archived })
+        .collect();
+    if let Some((active, archived)) = unknown {
+        date_buckets.push(DateBucket { date: "unknown".to_string(), active, archived });
+    }
+    Ok(date_buckets)
+}
+
 // 获取存在但当前没有窗口的便签（即隐藏的便签）
 #[tauri::command]
 async fn get_notes_without_windows(window: tauri::WebviewWindow) -> Result<Vec<NoteEntry>, String> {
-    // 克隆window以便后面使用
-    let window_clone = window.clone();
     let app_handle = window.app_handle().clone();
-    let all_windows = app_handle.webview_windows();
-    
-    let notes_dir = PathBuf::from(ensure_notes_directory(window_clone).await?);
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
     let index = validate_and_fix_index(&notes_dir)?;
-    
-    let mut hidden_notes = Vec::new();
-    for entry in &index.notes {
-        if is_active(entry) && entry.window.is_some() {  // 活跃且应该有窗口
-            let label = format!("note-{}", entry.id);
-            
-            // 检查该标签的窗口是否存在
-            if let Some(note_window) = all_windows.get(&label) {
-                // 检查窗口是否可见
-                if let Ok(is_visible) = note_window.is_visible() {
-                    if !is_visible {
-                        // 窗口存在但不可见，需要恢复
-                        hidden_notes.push(entry.clone());
-                    }
-                } else {
-                    // 如果无法获取可见性状态，也认为是隐藏的
-                    hidden_notes.push(entry.clone());
-                }
-            } else {
-                // 窗口不存在，需要创建
-                hidden_notes.push(entry.clone());
+
+    // 窗口不存在需要创建，存在但不可见（或无法获取可见性）需要显示
+    let hidden_ids: Vec<String> = note_window_states(&app_handle, &index).into_iter()
+        .filter(|state| !state.visible)
+        .map(|state| state.id)
+        .collect();
+    Ok(index.notes.into_iter()
+        .filter(|entry| hidden_ids.contains(&entry.id))
+        .collect())
+}
+
+// 按 note-{id} 标签匹配每个活跃便签的窗口，无法获取可见性或已最小化时视为不可见
+fn note_window_states(app_handle: &tauri::AppHandle, index: &IndexFile) -> Vec<NoteWindowState> {
+    let all_windows = app_handle.webview_windows();
+    index.notes.iter()
+        .filter(|entry| is_active(entry))
+        .map(|entry| {
+            let note_window = all_windows.get(&format!("note-{}", entry.id));
+            NoteWindowState {
+                id: entry.id.clone(),
+                window_exists: note_window.is_some(),
+                visible: note_window.is_some_and(|note_window| !is_note_window_minimized(note_window) && note_window.is_visible().unwrap_or(false)),
             }
-        } else if is_active(entry) && entry.window.is_none() {  // 活跃但没有窗口配置
-            hidden_notes.push(entry.clone());
-        }
+        })
+        .collect()
+}
+
+fn is_note_window_minimized(note_window: &tauri::WebviewWindow) -> bool {
+    note_window.state::<AppState>().minimized_labels.lock().unwrap().contains(note_window.label())
+        || note_window.is_minimized().unwrap_or(false)
+}
+
+// 最小化便签窗口到任务栏/程序坞，便签保持活跃，可通过「显示便签」恢复；与关闭按钮的隐藏互不影响
+#[tauri::command]
+async fn minimize_note(window: tauri::WebviewWindow, id: String) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+    if !index.notes.iter().any(|entry| entry.id == id && is_active(entry)) {
+        return Err("找不到指定的便签".to_string());
     }
 
-    Ok(hidden_notes)
+    let label = format!("note-{}", id);
+    let note_window = app_handle.get_webview_window(&label)
+        .ok_or_else(|| "便签窗口不存在".to_string())?;
+    note_window.minimize().map_err(|e| e.to_string())?;
+    app_handle.state::<AppState>().minimized_labels.lock().unwrap().insert(label);
+    Ok(())
+}
+
+// 列出每个活跃便签的窗口是否存在、是否可见
+#[tauri::command]
+async fn list_open_note_windows(window: tauri::WebviewWindow) -> Result<Vec<NoteWindowState>, String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+    Ok(note_window_states(&app_handle, &index))
 }
 
 // 恢复没有窗口的便签（为它们创建窗口）
@@ -808,345 +1917,2652 @@ async fn restore_notes_without_windows(window: tauri::WebviewWindow) -> Result<(
     
     let app_handle = window.app_handle().clone();
     for note in notes_without_windows {
-        // 为便签创建默认窗口位置
-        let default_x = 100.0 + (note.id.as_bytes()[0] as f64 * 20.0) % 200.0;
-        let default_y = 100.0 + (note.id.as_bytes()[1] as f64 * 20.0) % 200.0;
-        
-        let window_info = note.window.unwrap_or(WindowInfo {
-            x: default_x,
-            y: default_y,
-            width: 280.0,
-            height: 360.0,
-        });
-        
-        let label = format!("note-{}", note.id);
-        let _ = create_note_window(
-            app_handle.clone(),
-            label,
-            "FadeNote".to_string(),
-            window_info.width as u32,
-            window_info.height as u32,
-            Some(window_info.x as i32),
-            Some(window_info.y as i32),
-        ).await;
+        let _ = open_note_window(&app_handle, &note).await;
     }
     
     Ok(())
 }
 
-// 创建新的便签
-#[tauri::command]
-async fn create_note(window: tauri::WebviewWindow, x: f64, y: f64, width: f64, height: f64) -> Result<String, String> {
-    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
-    
-    // 生成UUID作为ID
-    let id = Uuid::new_v4().to_string();
-    
-    // 创建时间信息
-    let created_at = get_current_iso8601_time();
-    let expires_at = expire_at_7_days_from_iso(&created_at)?;
-    
-    // 创建文件内容
-    let content = build_full_content(&id, &created_at, "");
-    
-    // 创建按日期组织的目录结构
-    let today = Utc::now().format("%Y-%m-%d").to_string();
-    let dated_dir = notes_dir.join("notes").join(today);
-    fs::create_dir_all(&dated_dir).map_err(|e| format!("创建日期目录失败: {}", e))?;
-
-    // 创建文件
-    let file_path = dated_dir.join(format!("{}.md", id));
-    write_file_safely(&file_path, content).map_err(|e| format!("创建便签文件失败: {}", e))?;
+// 没有保存坐标的便签按完整ID哈希（FNV-1a）出一个稳定的默认位置，保证默认大小的窗口落在主显示器工作区内
+// 获取不到主显示器时按 1280x800 的区域计算
+fn default_window_position(app_handle: &tauri::AppHandle, id: &str) -> (f64, f64) {
+    let work_area = app_handle.primary_monitor().ok().flatten()
+        .map(|monitor| monitor_work_area(&monitor))
+        .unwrap_or((0.0, 0.0, 1280.0, 800.0));
+    default_position_in_area(id, work_area)
+}
 
-    // 更新索引
-    let index_path = notes_dir.join("index.json");
-    let mut index: IndexFile = if index_path.exists() {
-        let content = fs::read_to_string(&index_path)
-            .map_err(|e| format!("读取索引文件失败: {}", e))?;
-        serde_json::from_str(&content)
-            .map_err(|e| format!("解析索引文件失败: {}", e))?
-    } else {
-        new_empty_index()
-    };
+// 在工作区 (x, y, width, height) 内按ID哈希取默认大小窗口的左上角；工作区比窗口小时贴在工作区左上角
+fn default_position_in_area(id: &str, (area_x, area_y, area_width, area_height): (f64, f64, f64, f64)) -> (f64, f64) {
+    let hash = id.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    let free_width = (area_width - DEFAULT_NOTE_WIDTH).max(0.0);
+    let free_height = (area_height - DEFAULT_NOTE_HEIGHT).max(0.0);
+    let x = area_x + (hash % 1000) as f64 / 1000.0 * free_width;
+    let y = area_y + ((hash >> 32) % 1000) as f64 / 1000.0 * free_height;
+    (x.round(), y.round())
+}
 
-    let rel_path = file_path.strip_prefix(&notes_dir)
-        .unwrap_or(&file_path)
-        .to_string_lossy()
-        .to_string();
+// 显示便签窗口：窗口已存在则显示并聚焦，否则按保存的（或默认的）位置创建
+async fn open_note_window(app_handle: &tauri::AppHandle, note: &NoteEntry) -> Result<(), String> {
+    let label = format!("note-{}", note.id);
+    if let Some(note_window) = app_handle.get_webview_window(&label) {
+        let _ = note_window.show();
+        let _ = note_window.unminimize();
+        let _ = note_window.set_focus();
+        return Ok(());
+    }
 
-    let mut new_entry = NoteEntry {
-        id: id.clone(),
-        created_at: created_at.clone(),
-        last_active_at: created_at.clone(), // 初始last_active_at就是创建时间
-        expire_at: Some(expires_at.clone()),
-        cached_preview: None,
-        status: String::new(), // 禁止手写，将在派生时设置
-        archived_at: None,
-        window: Some(WindowInfo {
-            x,
-            y,
-            width,
-            height,
-        }),
-        pinned: false,  // 默认不固定
-        file: FileInfo {
-            relative_path: rel_path,
-        },
-    };
+    // 为便签创建默认窗口位置
+    let (default_x, default_y) = default_window_position(app_handle, &note.id);
     
-    // 派生状态
-    derive_status(&mut new_entry);
+    let window_info = note.window.clone().unwrap_or(WindowInfo {
+        x: default_x,
+        y: default_y,
+        width: DEFAULT_NOTE_WIDTH,
+        height: DEFAULT_NOTE_HEIGHT,
+        decorated: false,
+        on_all_desktops: false,
+        monitor_index: None,
+    });
     
-    index.notes.push(new_entry);
+    create_note_window(
+        app_handle.clone(),
+        label,
+        window_title_from_preview(note.cached_preview.as_ref()),
+        window_info.width as u32,
+        window_info.height as u32,
+        Some(window_info.x as i32),
+        Some(window_info.y as i32),
+    ).await
+}
 
-    let json_content = serde_json::to_string_pretty(&index)
-        .map_err(|e| format!("序列化索引失败: {}", e))?;
-        write_file_safely(&index_path, json_content)
-            .map_err(|e| format!("写入索引文件失败: {}", e))?;
+// 去掉首尾空白和空标签，并按首次出现的顺序去重
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_string();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
 
-    Ok(id)
+// 设置便签标签（元数据，不修改 lastActiveAt / expireAt）
+#[tauri::command]
+async fn set_note_tags(window: tauri::WebviewWindow, id: String, tags: Vec<String>) -> Result<Vec<String>, String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    let entry = index.notes.iter_mut().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    apply_note_tags(entry, tags);
+    let tags = entry.tags.clone();
+    save_index(&notes_dir, &mut index)?;
+    emit_index_changed(&app_handle, "updated", vec![id.clone()]);
+    Ok(tags)
 }
 
-// 读取便签内容
+// 批量为多个便签添加 / 移除标签，只写一次索引；找不到的ID记入 not_found，不影响其他便签
+// 同一个标签同时出现在 add 和 remove 中时以移除为准
 #[tauri::command]
-async fn load_note(window: tauri::WebviewWindow, id: String) -> Result<Option<String>, String> {
+async fn set_tags_bulk(window: tauri::WebviewWindow, ids: Vec<String>, add: Vec<String>, remove: Vec<String>) -> Result<BulkTagsReport, String> {
+    let app_handle = window.app_handle().clone();
     let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
-    
-    let index_path = notes_dir.join("index.json");
-    if !index_path.exists() {
-        return Ok(None);
-    }
+    let mut index = read_index(&notes_dir)?;
+    let add = normalize_tags(add);
+    let remove = normalize_tags(remove);
 
-    let index: IndexFile = {
-        let content = fs::read_to_string(&index_path)
-            .map_err(|e| format!("读取索引文件失败: {}", e))?;
-        serde_json::from_str(&content)
-            .map_err(|e| format!("解析索引文件失败: {}", e))?
+    let mut report = BulkTagsReport {
+        updated: Vec::new(),
+        not_found: Vec::new(),
     };
+    for id in ids {
+        let entry = match index.notes.iter_mut().find(|note| note.id == id) {
+            Some(entry) => entry,
+            None => {
+                report.not_found.push(id);
+                continue;
+            }
+        };
+        let mut tags = std::mem::take(&mut entry.tags);
+        tags.extend(add.iter().cloned());
+        entry.tags = normalize_tags(tags).into_iter()
+            .filter(|tag| !remove.contains(tag))
+            .collect();
+        report.updated.push(NoteTags {
+            id,
+            tags: entry.tags.clone(),
+        });
+    }
 
-    // 在索引中查找该ID的便签
-    let note = index.notes.iter().find(|note| note.id == id);
-    
-    if let Some(entry) = note {
-        if !is_active(entry) {
-            return Ok(None);
-        }
-        let file_path = notes_dir.join(&entry.file.relative_path);
-        if file_path.exists() {
-            let full_content = fs::read_to_string(&file_path)
-                .map_err(|e| format!("读取便签文件失败: {}", e))?;
-            let pure_content = extract_content_only(&full_content);
-            Ok(Some(pure_content))
-        } else {
-            Ok(None)
-        }
-    } else {
-        Ok(None)
+    if !report.updated.is_empty() {
+        save_index(&notes_dir, &mut index)?;
+        let ids = report.updated.iter().map(|note| note.id.clone()).collect();
+        emit_index_changed(&app_handle, "updated", ids);
     }
+    Ok(report)
 }
 
-// 更新便签的活动时间
+// 快速切换：只用索引中的预览做不区分大小写的匹配，前缀匹配优先，其次按最近活跃排序
 #[tauri::command]
-async fn update_note_activity(window: tauri::WebviewWindow, id: String) -> Result<(), String> {
+async fn quick_switch(window: tauri::WebviewWindow, query: String) -> Result<Vec<QuickSwitchItem>, String> {
     let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
-    
-    // 从索引中获取文件路径
-    let index_path = notes_dir.join("index.json");
-    if !index_path.exists() {
-        return Err("索引文件不存在".to_string());
-    }
+    let index = read_index(&notes_dir)?;
+    let query = query.trim().to_lowercase();
+
+    let mut matches: Vec<(usize, &NoteEntry)> = index.notes.iter()
+        .filter(|entry| is_active(entry))
+        .filter_map(|entry| {
+            // 受保护的便签不参与预览匹配，只在空查询时列出
+            let preview = entry.cached_preview.as_deref().filter(|_| !hides_body(entry)).unwrap_or("").to_lowercase();
+            if query.is_empty() || preview.starts_with(&query) {
+                Some((0, entry))
+            } else if preview.contains(&query) {
+                Some((1, entry))
+            } else {
+                None
+            }
+        })
+        .collect();
+    matches.sort_by(|(rank_a, a), (rank_b, b)| {
+        rank_a.cmp(rank_b).then_with(|| b.last_active_at.cmp(&a.last_active_at))
+    });
+
+    Ok(matches.into_iter()
+        .map(|(_, entry)| QuickSwitchItem {
+            id: entry.id.clone(),
+            preview: entry.cached_preview.clone().filter(|_| !hides_body(entry)),
+        })
+        .collect())
+}
+
+// 便签的淡出进度（0.0–1.0），供前端在归档前逐渐淡化便签
+#[tauri::command]
+async fn fade_progress(window: tauri::WebviewWindow, id: String) -> Result<f64, String> {
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+    let entry = index.notes.iter().find(|entry| entry.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    Ok(fade_fraction(entry, &Local::now()))
+}
+
+// 按剩余寿命从短到长列出所有活跃便签，永不淡出的排在最后
+#[tauri::command]
+async fn get_active_notes_by_urgency(window: tauri::WebviewWindow) -> Result<Vec<NoteUrgency>, String> {
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+    let now = Local::now();
+
+    let mut notes: Vec<NoteUrgency> = index.notes.iter()
+        .filter(|entry| is_active(entry))
+        .map(|entry| NoteUrgency {
+            id: entry.id.clone(),
+            preview: entry.cached_preview.clone().filter(|_| !hides_body(entry)),
+            expire_at: entry.expire_at.clone(),
+            pinned: entry.pinned,
+            seconds_remaining: seconds_until_expiry(entry, &now),
+        })
+        .collect();
+    notes.sort_by_key(|note| (note.seconds_remaining.is_none(), note.seconds_remaining));
+    Ok(notes)
+}
+
+// 显示并聚焦指定便签，窗口不存在时按索引中的位置创建
+#[tauri::command]
+async fn focus_note(window: tauri::WebviewWindow, id: String) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+    let note = index.notes.iter().find(|entry| entry.id == id && is_active(entry))
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    open_note_window(&app_handle, note).await
+}
+
+// 设置便签贴边自动隐藏的边缘，None 表示取消；已打开的窗口先从原边缘滑回屏幕内，再滑出到新边缘
+#[tauri::command]
+async fn set_note_auto_hide_edge(window: tauri::WebviewWindow, id: String, edge: Option<String>) -> Result<(), String> {
+    if let Some(edge) = edge.as_deref() {
+        if !["left", "right", "top", "bottom"].contains(&edge) {
+            return Err(format!("无效的屏幕边缘: {}", edge));
+        }
+    }
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    let entry = index.notes.iter_mut().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    let previous_edge = std::mem::replace(&mut entry.auto_hide_edge, edge.clone());
+    save_index(&notes_dir, &mut index)?;
+    emit_index_changed(&app_handle, "updated", vec![id.clone()]);
+
+    let label = format!("note-{}", id);
+    if let Some(note_window) = app_handle.get_webview_window(&label) {
+        // 取消或换边时先把窗口从原边缘滑回屏幕内，否则换到相邻边缘时另一个方向仍停在屏幕外
+        let mut slides = Vec::new();
+        if let Some(previous_edge) = previous_edge.filter(|previous_edge| Some(previous_edge) != edge.as_ref()) {
+            slides.push((previous_edge, false));
+        }
+        if let Some(edge) = edge.clone() {
+            slides.push((edge, true));
+        }
+        spawn_slides(&app_handle, note_window, slides);
+        let _ = app_handle.emit_to(label.as_str(), "fadenote://auto-hide-edge-changed", edge);
+    }
+    Ok(())
+}
+
+// 把贴边便签滑出到屏幕外（只留 AUTO_HIDE_PEEK 像素）或滑回紧贴该边缘的位置
+// 动画在单独的线程中进行，命令立即返回；拿不到显示器信息或平台不允许移动窗口（如 Wayland）时只记录日志
+#[tauri::command]
+async fn slide_note_window(window: tauri::WebviewWindow, id: String, hidden: bool) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+    let edge = index.notes.iter()
+        .find(|note| note.id == id)
+        .and_then(|note| note.auto_hide_edge.clone())
+        .ok_or_else(|| "该便签未开启贴边隐藏".to_string())?;
+
+    let note_window = app_handle.get_webview_window(&format!("note-{}", id))
+        .ok_or_else(|| "便签窗口不存在".to_string())?;
+    spawn_slides(&app_handle, note_window, vec![(edge, hidden)]);
+    Ok(())
+}
+
+// 在单独的线程中依次执行贴边动画（边缘, 是否隐藏），不占用异步运行时
+// 同一窗口开始新的动画后，旧的动画在下一帧停止
+fn spawn_slides(app_handle: &tauri::AppHandle, note_window: tauri::WebviewWindow, slides: Vec<(String, bool)>) {
+    if slides.is_empty() {
+        return;
+    }
+    let label = note_window.label().to_string();
+    let generation = {
+        let app_state = app_handle.state::<AppState>();
+        let mut slide_generations = app_state.slide_generations.lock().unwrap();
+        let generation = slide_generations.entry(label.clone()).or_insert(0);
+        *generation += 1;
+        *generation
+    };
+
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        let is_current = || app_handle.state::<AppState>().slide_generations.lock().unwrap().get(&label) == Some(&generation);
+        for (edge, hidden) in slides {
+            if let Err(e) = slide_to_edge(&note_window, &edge, hidden, &is_current) {
+                warn!(label = %label, error = %e, "贴边便签移动失败");
+                return;
+            }
+        }
+    });
+}
+
+// 逐帧移动到目标位置；is_current 返回 false（有更新的动画）时提前停止
+fn slide_to_edge(note_window: &tauri::WebviewWindow, edge: &str, hidden: bool, is_current: &dyn Fn() -> bool) -> Result<(), String> {
+    let monitor = match note_window.current_monitor().map_err(|e| e.to_string())? {
+        Some(monitor) => monitor,
+        None => note_window.primary_monitor().map_err(|e| e.to_string())?
+            .ok_or_else(|| "无法获取显示器信息".to_string())?,
+    };
+    let start = note_window.outer_position().map_err(|e| e.to_string())?;
+    let size = note_window.outer_size().map_err(|e| e.to_string())?;
+    let (monitor_x, monitor_y) = (monitor.position().x, monitor.position().y);
+    let (monitor_width, monitor_height) = (monitor.size().width as i32, monitor.size().height as i32);
+    let (width, height) = (size.width as i32, size.height as i32);
+
+    let (target_x, target_y) = match (edge, hidden) {
+        ("left", true) => (monitor_x - width + AUTO_HIDE_PEEK, start.y),
+        ("left", false) => (monitor_x, start.y),
+        ("right", true) => (monitor_x + monitor_width - AUTO_HIDE_PEEK, start.y),
+        ("right", false) => (monitor_x + monitor_width - width, start.y),
+        ("top", true) => (start.x, monitor_y - height + AUTO_HIDE_PEEK),
+        ("top", false) => (start.x, monitor_y),
+        ("bottom", true) => (start.x, monitor_y + monitor_height - AUTO_HIDE_PEEK),
+        _ => (start.x, monitor_y + monitor_height - height),
+    };
+
+    for step in 1..=AUTO_HIDE_STEPS {
+        if !is_current() {
+            return Ok(());
+        }
+        let x = start.x + (target_x - start.x) * step / AUTO_HIDE_STEPS;
+        let y = start.y + (target_y - start.y) * step / AUTO_HIDE_STEPS;
+        note_window.set_position(tauri::PhysicalPosition::new(x, y))
+            .map_err(|e| e.to_string())?;
+        std::thread::sleep(AUTO_HIDE_FRAME);
+    }
+    Ok(())
+}
+
+// 隐藏指定便签的窗口（与 focus_note 对应），便签在索引中保持不变；窗口不存在或已隐藏也视为成功
+#[tauri::command]
+async fn hide_note(window: tauri::WebviewWindow, id: String) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+    if !index.notes.iter().any(|entry| entry.id == id) {
+        return Err("找不到指定的便签".to_string());
+    }
+
+    if let Some(note_window) = app_handle.get_webview_window(&format!("note-{}", id)) {
+        note_window.hide().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// 显示带有指定标签的所有活跃便签，把标签当作轻量的工作区
+#[tauri::command]
+async fn show_notes_with_tag(window: tauri::WebviewWindow, tag: String) -> Result<Vec<String>, String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = validate_and_fix_index(&notes_dir)?;
+
+    let mut shown_ids = Vec::new();
+    for note in index.notes.iter().filter(|entry| is_active(entry) && entry.tags.contains(&tag)) {
+        match open_note_window(&app_handle, note).await {
+            Ok(_) => shown_ids.push(note.id.clone()),
+            Err(e) => error!(note_id = %note.id, error = %e, "恢复便签窗口失败"),
+        }
+    }
+    Ok(shown_ids)
+}
+
+// 隐藏带有指定标签的所有便签窗口，便签在索引中保持活跃
+#[tauri::command]
+async fn hide_notes_with_tag(window: tauri::WebviewWindow, tag: String) -> Result<Vec<String>, String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+
+    let mut hidden_ids = Vec::new();
+    for note in index.notes.iter().filter(|entry| is_active(entry) && entry.tags.contains(&tag)) {
+        if let Some(note_window) = app_handle.get_webview_window(&format!("note-{}", note.id)) {
+            let _ = note_window.hide();
+            hidden_ids.push(note.id.clone());
+        }
+    }
+    Ok(hidden_ids)
+}
+
+// 统计 notes 目录占用的磁盘空间（只读）
+// .md 文件按索引区分活跃/归档，其余文件计为附件
+#[tauri::command]
+async fn storage_usage(window: tauri::WebviewWindow) -> Result<StorageUsage, String> {
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+    let archived_paths: std::collections::HashSet<PathBuf> = index.notes.iter()
+        .filter(|entry| !is_active(entry))
+        .map(|entry| notes_dir.join(&entry.file.relative_path))
+        .collect();
+
+    let mut usage = StorageUsage {
+        total_bytes: 0,
+        note_count: 0,
+        archived_bytes: 0,
+        attachment_bytes: 0,
+    };
+    for (path, size) in collect_file_sizes(&notes_dir.join("notes")) {
+        usage.total_bytes += size;
+        if path.extension().is_some_and(|ext| ext == "md") {
+            usage.note_count += 1;
+            if archived_paths.contains(&path) {
+                usage.archived_bytes += size;
+            }
+        } else {
+            usage.attachment_bytes += size;
+        }
+    }
+
+    Ok(usage)
+}
+
+// 导出当前索引的 JSON 快照
+#[tauri::command]
+async fn export_index_json(window: tauri::WebviewWindow) -> Result<String, String> {
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+    serde_json::to_string_pretty(&index)
+        .map_err(|e| format!("序列化索引失败: {}", e))
+}
+
+// 把所有便签正文（去掉 Front Matter）按创建时间拼接成一个 Markdown 文件，返回导出的便签数
+// 每篇前面加一行「## 创建时间 · 预览」标题；include_archived 为 false 时只导出活跃便签
+// 文件缺失、正文已加密或受口令保护的便签跳过
+#[tauri::command]
+async fn export_combined(window: tauri::WebviewWindow, dest_path: String, include_archived: bool, separator: Option<String>) -> Result<usize, String> {
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+    let separator = separator.unwrap_or_else(|| "\n\n---\n\n".to_string());
+
+    let mut notes: Vec<&NoteEntry> = index.notes.iter()
+        .filter(|entry| include_archived || is_active(entry))
+        .collect();
+    // 无法解析的创建时间排在最前
+    notes.sort_by_key(|entry| DateTime::parse_from_rfc3339(&entry.created_at).ok());
+
+    let mut sections = Vec::new();
+    for entry in notes {
+        let content = match fs::read_to_string(notes_dir.join(&entry.file.relative_path)) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!(note_id = %entry.id, error = %e, "导出时读取便签失败，已跳过");
+                continue;
+            }
+        };
+        let body = extract_content_only(&content);
+        if hides_body(entry) {
+            continue;
+        }
+        let header = match entry.cached_preview.as_deref() {
+            Some(preview) => format!("## {} · {}", entry.created_at, preview),
+            None => format!("## {}", entry.created_at),
+        };
+        sections.push(format!("{}\n\n{}", header, body.trim_end()));
+    }
+
+    write_file_safely(&dest_path, format!("{}\n", sections.join(&separator)))
+        .map_err(|e| format!("写入导出文件失败: {}", e))?;
+    Ok(sections.len())
+}
+
+// 干净导出：把活跃和归档便签的正文按创建日期写入 dest_dir（Front Matter 只保留 createdAt），并写 manifest.json
+// 不修改数据目录中的索引和文件；加密、受保护和读取失败的便签跳过，返回导出的便签数
+#[tauri::command]
+async fn export_clean(window: tauri::WebviewWindow, dest_dir: String) -> Result<usize, String> {
+    let settings = load_schedule_settings_from_disk();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let dest_dir = PathBuf::from(dest_dir);
+    if dest_dir.starts_with(&notes_dir) {
+        return Err("导出目录不能位于数据目录内".to_string());
+    }
+    let index = read_index(&notes_dir)?;
+
+    let mut manifest = CleanExportManifest {
+        exported_at: get_current_iso8601_time(),
+        notes: Vec::new(),
+    };
+    for entry in &index.notes {
+        let content = match fs::read_to_string(notes_dir.join(&entry.file.relative_path)) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!(note_id = %entry.id, error = %e, "导出时读取便签失败，已跳过");
+                continue;
+            }
+        };
+        let body = extract_content_only(&content);
+        if hides_body(entry) {
+            continue;
+        }
+
+        let created_date = DateTime::parse_from_rfc3339(&entry.created_at)
+            .map(|time| time.with_timezone(&Local).date_naive())
+            .unwrap_or_else(|_| Local::now().date_naive());
+        let dated_dir = dated_note_dir(&dest_dir, created_date, &settings);
+        fs::create_dir_all(&dated_dir).map_err(|e| format!("创建日期目录失败: {}", e))?;
+        let file_path = dated_dir.join(format!("{}.md", entry.id));
+        write_file_safely(&file_path, format!("---\ncreatedAt: {}\n---\n{}", entry.created_at, body))
+            .map_err(|e| format!("写入导出文件失败: {}", e))?;
+
+        manifest.notes.push(CleanExportEntry {
+            id: entry.id.clone(),
+            relative_path: file_path.strip_prefix(&dest_dir)
+                .unwrap_or(&file_path)
+                .to_string_lossy()
+                .to_string(),
+            created_at: entry.created_at.clone(),
+            archived: !is_active(entry),
+            tags: entry.tags.clone(),
+        });
+    }
+
+    let manifest_content = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("序列化导出清单失败: {}", e))?;
+    write_file_safely(dest_dir.join("manifest.json"), manifest_content)
+        .map_err(|e| format!("写入导出清单失败: {}", e))?;
+    Ok(manifest.notes.len())
+}
+
+// 导入索引 JSON 快照，reconcile 为 true 时丢弃磁盘上找不到文件的条目
+#[tauri::command]
+async fn import_index_json(window: tauri::WebviewWindow, json: String, reconcile: Option<bool>) -> Result<usize, String> {
+    let app_handle = window.app_handle().clone();
+    let mut imported: IndexFile = serde_json::from_str(&json)
+        .map_err(|e| format!("解析索引文件失败: {}", e))?;
+    if imported.version != INDEX_VERSION {
+        return Err(format!("不支持的索引版本: {}", imported.version));
+    }
+
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    // 不信任导入的路径：每个 relativePath 都必须落在数据目录内
+    for entry in &imported.notes {
+        resolve_relative_path(&notes_dir, &entry.file.relative_path)?;
+    }
+
+    if reconcile.unwrap_or(false) {
+        imported.notes.retain(|entry| notes_dir.join(&entry.file.relative_path).is_file());
+    }
+
+    let mut index = normalize_index(imported);
+    save_index(&notes_dir, &mut index)?;
+    emit_index_changed(&app_handle, "updated", index.notes.iter().map(|entry| entry.id.clone()).collect());
+    Ok(index.notes.len())
+}
+
+// 列出 notes 目录中 id 不在索引里的 .md 文件
+#[tauri::command]
+async fn get_orphan_files(window: tauri::WebviewWindow) -> Result<Vec<OrphanFile>, String> {
+    let settings = load_schedule_settings_from_disk();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+    let known_ids: std::collections::HashSet<&str> = index.notes.iter().map(|note| note.id.as_str()).collect();
+    let global_salt = global_encryption_salt(&settings);
+
+    let mut orphans = Vec::new();
+    for path in collect_markdown_files(&notes_dir.join("notes")) {
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        if let Some(parsed_id) = parse_id_from_content(&content) {
+            if !known_ids.contains(parsed_id.as_str()) {
+                let body = extract_content_only(&content);
+                orphans.push(OrphanFile {
+                    id: parsed_id,
+                    relative_path: path.strip_prefix(&notes_dir)
+                        .unwrap_or(&path)
+                        .to_string_lossy()
+                        .to_string(),
+                    // 孤立文件没有索引标记，只要正文是密文结构就不生成预览
+                    preview: match detect_legacy_encryption(&body, true, global_salt.as_deref()) {
+                        BodyEncryption::None => note_preview(&body, &settings),
+                        _ => None,
+                    },
+                });
+            }
+        }
+    }
+
+    Ok(orphans)
+}
+
+// 计算所有活跃便签正文的内容哈希，读取失败的文件和加密便签跳过
+// 哈希按文件路径缓存，文件的修改时间和大小都没变时直接用缓存，不再重新读取
+fn active_content_hashes(app_handle: &tauri::AppHandle, notes_dir: &Path, index: &IndexFile) -> std::collections::HashSet<String> {
+    let app_state = app_handle.state::<AppState>();
+    let mut cache = app_state.content_hashes.lock().unwrap();
+    index.notes.iter()
+        .filter(|note| is_active(note) && !is_encrypted(note))
+        .filter_map(|note| {
+            let path = notes_dir.join(&note.file.relative_path);
+            let metadata = fs::metadata(&path).ok()?;
+            let stamp = (metadata.modified().ok()?, metadata.len());
+            if let Some((cached_stamp, hash)) = cache.get(&path) {
+                if *cached_stamp == stamp {
+                    return Some(hash.clone());
+                }
+            }
+            let hash = content_hash(&extract_content_only(&fs::read_to_string(&path).ok()?));
+            cache.insert(path, (stamp, hash.clone()));
+            Some(hash)
+        })
+        .collect()
+}
+
+// 从外部 .md 文件导入便签，文件复制到按日期组织的目录中，索引只在最后写入一次
+// Front Matter 中的 id 无效或与现有便签冲突时换用新ID并计入 remapped（没有 id 的文件直接用新ID）
+// skip_duplicate_content 为 true 时，正文与某个活跃便签（或本次已导入的文件）相同的文件跳过，计入 skippedDuplicates
+#[tauri::command]
+async fn import_notes(window: tauri::WebviewWindow, paths: Vec<String>, skip_duplicate_content: Option<bool>) -> Result<ImportReport, String> {
+    let settings = load_schedule_settings_from_disk();
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index_or_empty(&notes_dir)?;
+    let window_info = default_note_window_info(&notes_dir);
+    let skip_duplicate_content = skip_duplicate_content.unwrap_or(false);
+    let mut known_hashes = if skip_duplicate_content {
+        active_content_hashes(&app_handle, &notes_dir, &index)
+    } else {
+        std::collections::HashSet::new()
+    };
+
+    let mut report = ImportReport {
+        imported: Vec::new(),
+        remapped: 0,
+        skipped_duplicates: 0,
+        failed: Vec::new(),
+    };
+    for path in paths {
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!(path = %path, error = %e, "导入时读取文件失败，已跳过");
+                report.failed.push(path);
+                continue;
+            }
+        };
+        let body = extract_content_only(&content);
+        if check_note_size(&body, &settings).is_err() {
+            report.failed.push(path);
+            continue;
+        }
+        if skip_duplicate_content && !known_hashes.insert(content_hash(&body)) {
+            report.skipped_duplicates += 1;
+            continue;
+        }
+
+        // id 会用作文件名，只接受 UUID
+        let id = match parse_id_from_content(&content) {
+            Some(id) if Uuid::parse_str(&id).is_ok() && !index.notes.iter().any(|note| note.id == id) => id,
+            Some(_) => {
+                report.remapped += 1;
+                Uuid::new_v4().to_string()
+            }
+            None => Uuid::new_v4().to_string(),
+        };
+        let created_at = extract_created_at_from_content(&content)
+            .filter(|value| DateTime::parse_from_rfc3339(value).is_ok());
+        // 写入失败（如开启了全局加密但密钥未加载）只记入 failed，已导入的文件照常写入索引
+        let new_entry = match write_note_file_as(&app_handle, &notes_dir, id, created_at, &body, window_info.clone(), &settings) {
+            Ok(new_entry) => new_entry,
+            Err(e) => {
+                warn!(path = %path, error = %e, "导入时写入便签失败，已跳过");
+                report.failed.push(path);
+                continue;
+            }
+        };
+        report.imported.push(new_entry.id.clone());
+        index.notes.push(new_entry);
+    }
+    if report.imported.is_empty() {
+        return Ok(report);
+    }
+    save_index(&notes_dir, &mut index)?;
+
+    emit_index_changed(&app_handle, "created", report.imported.clone());
+    if let Err(e) = apply_active_note_cap(&app_handle, &notes_dir, None, &settings) {
+        error!(error = %e, "自动归档超出上限的便签失败");
+    }
+    Ok(report)
+}
+
+// 为单个孤立文件创建新的索引条目，不影响其他便签的窗口信息
+// skip_duplicate_content 为 true 时，正文与某个活跃便签完全相同的文件不会被收养
+#[tauri::command]
+async fn adopt_orphan(window: tauri::WebviewWindow, relative_path: String, skip_duplicate_content: Option<bool>) -> Result<String, String> {
+    let settings = load_schedule_settings_from_disk();
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let file_path = resolve_relative_path(&notes_dir, &relative_path)?;
+    if !file_path.starts_with(notes_dir.join("notes")) || !file_path.is_file() {
+        return Err("便签文件不存在".to_string());
+    }
+
+    let content = fs::read_to_string(&file_path)
+        .map_err(|e| format!("读取便签文件失败: {}", e))?;
+    let parsed_id = parse_id_from_content(&content)
+        .ok_or_else(|| "无法从文件中解析ID".to_string())?;
+
+    let mut index = read_index(&notes_dir)?;
+    if index.notes.iter().any(|note| note.id == parsed_id) {
+        return Err("该便签已在索引中".to_string());
+    }
+    if skip_duplicate_content.unwrap_or(false) {
+        let active_hashes = active_content_hashes(&app_handle, &notes_dir, &index);
+        if active_hashes.contains(&content_hash(&extract_content_only(&content))) {
+            return Err("该文件内容与现有便签重复".to_string());
+        }
+    }
+
+    let now = Local::now();
+    let created_at = extract_created_at_from_content(&content)
+        .filter(|value| DateTime::parse_from_rfc3339(value).is_ok())
+        .unwrap_or_else(|| now.to_rfc3339());
+    let (default_x, default_y) = default_window_position(&app_handle, &parsed_id);
+    let body = extract_content_only(&content);
+    let (encryption, protected) = scanned_encryption(&body, None, &settings);
+
+    let rel_path = file_path.strip_prefix(&notes_dir)
+        .unwrap_or(&file_path)
+        .to_string_lossy()
+        .to_string();
+    let mut new_entry = NoteEntry {
+        last_active_at: now.to_rfc3339(),
+        expire_at: Some((now + Duration::days(7)).to_rfc3339()),
+        cached_preview: if encryption == BodyEncryption::None { note_preview(&body, &settings) } else { None },
+        protected,
+        encryption,
+        ..NoteEntry::new(parsed_id.clone(), created_at, rel_path, Some(WindowInfo {
+            x: default_x,
+            y: default_y,
+            width: DEFAULT_NOTE_WIDTH,
+            height: DEFAULT_NOTE_HEIGHT,
+            decorated: false,
+            on_all_desktops: false,
+            monitor_index: None,
+        }))
+    };
+    derive_status(&mut new_entry);
+    index.notes.push(new_entry);
+
+    save_index(&notes_dir, &mut index)?;
+    emit_index_changed(&app_handle, "created", vec![parsed_id.clone()]);
+    Ok(parsed_id)
+}
+
+// 校验每个便签文件的 Front Matter：id 必须与索引一致，createdAt 必须可解析（缺失时用索引中的值补齐）
+// 只重写需要修复的文件，返回修复的文件数
+#[tauri::command]
+async fn repair_front_matter(window: tauri::WebviewWindow) -> Result<usize, String> {
+    let settings = load_schedule_settings_from_disk();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+    let style = front_matter_style(&settings);
+
+    let mut fixed_count = 0;
+    for entry in &index.notes {
+        let file_path = notes_dir.join(&entry.file.relative_path);
+        let content = match fs::read_to_string(&file_path) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+
+        let id_matches = parse_id_from_content(&content).as_deref() == Some(entry.id.as_str());
+        let valid_created_at = extract_created_at_from_content(&content)
+            .filter(|created_at| DateTime::parse_from_rfc3339(created_at).is_ok());
+        if id_matches && valid_created_at.is_some() {
+            continue;
+        }
+
+        let created_at = valid_created_at.unwrap_or_else(|| entry.created_at.clone());
+        let body = strip_leading_front_matter(&content);
+        match write_file_safely(&file_path, build_full_content(&entry.id, &created_at, &body, style)) {
+            Ok(_) => fixed_count += 1,
+            Err(e) => error!(note_id = %entry.id, error = %e, "修复 Front Matter 失败"),
+        }
+    }
+    Ok(fixed_count)
+}
+
+// 为文件丢失的索引条目按 Front Matter 中的 id 重新查找文件，只更新 relativePath
+#[tauri::command]
+async fn repair_paths(window: tauri::WebviewWindow) -> Result<RepairPathsReport, String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    let missing_ids: Vec<String> = index.notes.iter()
+        .filter(|entry| !notes_dir.join(&entry.file.relative_path).is_file())
+        .map(|entry| entry.id.clone())
+        .collect();
+
+    let mut report = RepairPathsReport {
+        relinked: Vec::new(),
+        unresolved: Vec::new(),
+    };
+    if missing_ids.is_empty() {
+        return Ok(report);
+    }
+
+    let mut found_paths: std::collections::HashMap<String, String> = std::collections::HashMap::new();
+    for path in collect_markdown_files(&notes_dir.join("notes")) {
+        if let Some(parsed_id) = fs::read_to_string(&path).ok().and_then(|content| parse_id_from_content(&content)) {
+            if missing_ids.contains(&parsed_id) {
+                let relative_path = path.strip_prefix(&notes_dir)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .to_string();
+                found_paths.entry(parsed_id).or_insert(relative_path);
+            }
+        }
+    }
+
+    for entry in index.notes.iter_mut().filter(|entry| missing_ids.contains(&entry.id)) {
+        match found_paths.remove(&entry.id) {
+            Some(relative_path) => {
+                entry.file.relative_path = relative_path;
+                report.relinked.push(entry.id.clone());
+            }
+            None => report.unresolved.push(entry.id.clone()),
+        }
+    }
+
+    if !report.relinked.is_empty() {
+        save_index(&notes_dir, &mut index)?;
+        emit_index_changed(&app_handle, "updated", report.relinked.clone());
+    }
+    Ok(report)
+}
+
+// 手动把便签指向数据目录内的另一个文件，用于 repair_paths 自动查找选错文件的情况
+// 目标文件的 Front Matter id 与便签不一致时，按便签ID重写 Front Matter（保留可解析的 createdAt）
+#[tauri::command]
+async fn relink_note(window: tauri::WebviewWindow, id: String, relative_path: String) -> Result<(), String> {
+    let settings = load_schedule_settings_from_disk();
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    let data_dir = notes_dir.canonicalize()
+        .map_err(|e| format!("解析数据目录失败: {}", e))?;
+    let file_path = notes_dir.join(&relative_path).canonicalize()
+        .map_err(|e| format!("目标文件不存在: {}", e))?;
+    if !file_path.starts_with(&data_dir) || !file_path.is_file() {
+        return Err("目标文件必须位于数据目录内".to_string());
+    }
+    let relative_path = file_path.strip_prefix(&data_dir)
+        .unwrap_or(&file_path)
+        .to_string_lossy()
+        .to_string();
+    if index.notes.iter().any(|entry| entry.id != id && entry.file.relative_path == relative_path) {
+        return Err("目标文件已被其他便签使用".to_string());
+    }
+
+    let entry = index.notes.iter_mut().find(|entry| entry.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    let content = fs::read_to_string(&file_path)
+        .map_err(|e| format!("读取文件失败: {}", e))?;
+    if parse_id_from_content(&content).as_deref() != Some(id.as_str()) {
+        let created_at = extract_created_at_from_content(&content)
+            .filter(|created_at| DateTime::parse_from_rfc3339(created_at).is_ok())
+            .unwrap_or_else(|| entry.created_at.clone());
+        let body = strip_leading_front_matter(&content);
+        write_file_safely(&file_path, build_full_content(&id, &created_at, &body, front_matter_style(&settings)))
+            .map_err(|e| format!("重写 Front Matter 失败: {}", e))?;
+    }
+
+    entry.file.relative_path = relative_path;
+    save_index(&notes_dir, &mut index)?;
+    emit_index_changed(&app_handle, "updated", vec![id]);
+    Ok(())
+}
+
+// 创建新的便签
+#[tauri::command]
+async fn create_note(window: tauri::WebviewWindow, x: f64, y: f64, width: f64, height: f64) -> Result<String, String> {
+    let settings = load_schedule_settings_from_disk();
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let id = create_note_with_content(&app_handle, &notes_dir, "", WindowInfo { x, y, width, height, decorated: false, on_all_desktops: false, monitor_index: None }, &settings)?;
+    emit_index_changed(&app_handle, "created", vec![id.clone()]);
+    if let Err(e) = apply_active_note_cap(&app_handle, &notes_dir, Some(&id), &settings) {
+        error!(error = %e, "自动归档超出上限的便签失败");
+    }
+    Ok(id)
+}
+
+// 从剪贴板文本创建新的便签
+#[tauri::command]
+async fn create_note_from_clipboard(window: tauri::WebviewWindow, x: f64, y: f64, width: f64, height: f64) -> Result<String, String> {
+    let settings = load_schedule_settings_from_disk();
+    // 剪贴板为空或不是文本时，退化为创建空便签
+    let body = window.app_handle().clipboard().read_text().unwrap_or_default();
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let id = create_note_with_content(&app_handle, &notes_dir, &body, WindowInfo { x, y, width, height, decorated: false, on_all_desktops: false, monitor_index: None }, &settings)?;
+    emit_index_changed(&app_handle, "created", vec![id.clone()]);
+    if let Err(e) = apply_active_note_cap(&app_handle, &notes_dir, Some(&id), &settings) {
+        error!(error = %e, "自动归档超出上限的便签失败");
+    }
+    Ok(id)
+}
+
+// 创建便签文件并写入索引，返回新便签ID
+fn create_note_with_content(app_handle: &tauri::AppHandle, notes_dir: &Path, body: &str, window_info: WindowInfo, settings: &ScheduleSettings) -> Result<String, String> {
+    let new_entry = write_new_note_file(app_handle, notes_dir, body, window_info, settings)?;
+    let id = new_entry.id.clone();
+
+    // 更新索引
+    let mut index = read_index_or_empty(notes_dir)?;
+    index.notes.push(new_entry);
+    save_index(notes_dir, &mut index)?;
+
+    Ok(id)
+}
+
+// 生成新ID、写入便签文件并返回对应的索引条目（不写索引）；开启全局加密时正文加密写入
+fn write_new_note_file(app_handle: &tauri::AppHandle, notes_dir: &Path, body: &str, window_info: WindowInfo, settings: &ScheduleSettings) -> Result<NoteEntry, String> {
+    // 生成UUID作为ID
+    write_note_file_as(app_handle, notes_dir, Uuid::new_v4().to_string(), None, body, window_info, settings)
+}
+
+// 用指定的ID写入便签文件并返回索引条目（不写索引）；created_at 为 None 时取当前时间
+// 生命周期总是从现在开始：lastActiveAt 为当前时间，7 天后过期
+fn write_note_file_as(app_handle: &tauri::AppHandle, notes_dir: &Path, id: String, created_at: Option<String>, body: &str, window_info: WindowInfo, settings: &ScheduleSettings) -> Result<NoteEntry, String> {
+    let (file_body, preview, encryption) = plain_body_for_write(app_handle, body, settings)?;
+    let mut new_entry = write_stored_note_file(notes_dir, id, created_at, &file_body, Some(window_info), settings)?;
+    new_entry.cached_preview = preview;
+    new_entry.encryption = encryption;
+    Ok(new_entry)
+}
+
+// write_note_file_as 的写文件部分：正文按原样写入（不加密、不生成预览），返回明文条目
+fn write_stored_note_file(notes_dir: &Path, id: String, created_at: Option<String>, file_body: &str, window: Option<WindowInfo>, settings: &ScheduleSettings) -> Result<NoteEntry, String> {
+    // 创建时间信息
+    let now = get_current_iso8601_time();
+    let created_at = created_at.unwrap_or_else(|| now.clone());
+    let expires_at = expire_at_7_days_from_iso(&now)?;
+    
+    // 创建文件内容
+    let content = build_full_content(&id, &created_at, file_body, front_matter_style(settings));
+    
+    // 创建按日期组织的目录结构
+    let dated_dir = dated_note_dir(notes_dir, Utc::now().date_naive(), settings);
+    fs::create_dir_all(&dated_dir).map_err(|e| format!("创建日期目录失败: {}", e))?;
+
+    // 创建文件
+    let file_path = dated_dir.join(format!("{}.md", id));
+    write_file_safely(&file_path, content).map_err(|e| format!("创建便签文件失败: {}", e))?;
+
+    let rel_path = file_path.strip_prefix(notes_dir)
+        .unwrap_or(&file_path)
+        .to_string_lossy()
+        .to_string();
+
+    let mut new_entry = NoteEntry {
+        last_active_at: now.clone(), // 初始last_active_at就是写入时间
+        expire_at: Some(expires_at),
+        ..NoteEntry::new(id, created_at, rel_path, window)
+    };
+    
+    // 派生状态
+    record_note_event(&mut new_entry, "created", &now);
+    derive_status(&mut new_entry);
+    Ok(new_entry)
+}
+
+// 批量创建便签：每段正文一个便签（各自的ID和文件），索引只在最后写入一次，按输入顺序返回新ID
+// 任一正文超出大小上限时不创建任何便签；中途写文件或写索引失败时删除已写出的文件，不留下半批孤立文件
+#[tauri::command]
+async fn create_notes_bulk(window: tauri::WebviewWindow, bodies: Vec<String>) -> Result<Vec<String>, String> {
+    let settings = load_schedule_settings_from_disk();
+    for body in &bodies {
+        check_note_size(body, &settings)?;
+    }
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index_or_empty(&notes_dir)?;
+    let window_info = default_note_window_info(&notes_dir);
+
+    let mut new_entries = Vec::new();
+    let written = bodies.iter().try_for_each(|body| {
+        new_entries.push(write_new_note_file(&app_handle, &notes_dir, body, window_info.clone(), &settings)?);
+        Ok::<(), String>(())
+    });
+    if new_entries.is_empty() {
+        return written.map(|_| Vec::new());
+    }
+    let new_ids: Vec<String> = new_entries.iter().map(|entry| entry.id.clone()).collect();
+    let file_paths: Vec<PathBuf> = new_entries.iter().map(|entry| notes_dir.join(&entry.file.relative_path)).collect();
+    index.notes.extend(new_entries);
+    if let Err(e) = written.and_then(|_| save_index(&notes_dir, &mut index)) {
+        for file_path in &file_paths {
+            let _ = fs::remove_file(file_path);
+        }
+        return Err(e);
+    }
+
+    emit_index_changed(&app_handle, "created", new_ids.clone());
+    if let Err(e) = apply_active_note_cap(&app_handle, &notes_dir, None, &settings) {
+        error!(error = %e, "自动归档超出上限的便签失败");
+    }
+    Ok(new_ids)
+}
+
+// 把便签当前正文复制为一条新的归档快照（新ID、无窗口），原便签保持不变，返回快照ID
+#[tauri::command]
+async fn snapshot_note(window: tauri::WebviewWindow, id: String) -> Result<String, String> {
+    let settings = load_schedule_settings_from_disk();
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    let source = index.notes.iter().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    let source_content = fs::read_to_string(notes_dir.join(&source.file.relative_path))
+        .map_err(|e| format!("读取便签文件失败: {}", e))?;
+    let body = extract_content_only(&source_content);
+    let cached_preview = if hides_body(source) { None } else { note_preview(&body, &settings) };
+    let tags = source.tags.clone();
+    let markdown = source.markdown;
+    // 加密便签的快照沿用同一口令和加密方式，原样复制密文才能被解开，因此不经过 write_note_file_as 的加密
+    let protected = source.protected.clone();
+    let encryption = source.encryption;
+
+    let mut snapshot_entry = write_stored_note_file(&notes_dir, Uuid::new_v4().to_string(), None, &body, None, &settings)?;
+    let snapshot_id = snapshot_entry.id.clone();
+    snapshot_entry.cached_preview = cached_preview;
+    snapshot_entry.tags = tags;
+    snapshot_entry.markdown = markdown;
+    snapshot_entry.protected = protected;
+    snapshot_entry.encryption = encryption;
+    archive_note(&mut snapshot_entry, &Local::now())?;
+    derive_status(&mut snapshot_entry);
+    index.notes.push(snapshot_entry);
+
+    save_index(&notes_dir, &mut index)?;
+    emit_index_changed(&app_handle, "archived", vec![snapshot_id.clone()]);
+    Ok(snapshot_id)
+}
+
+// 读取便签内容
+// 读取便签文件，超过 max_bytes 时只读取前 max_bytes 字节（按 UTF-8 字符边界截断）
+fn read_note_file_limited(file_path: &Path, max_bytes: u64) -> Result<(String, bool), String> {
+    let file = fs::File::open(file_path)
+        .map_err(|e| format!("读取便签文件失败: {}", e))?;
+    let file_size = file.metadata()
+        .map_err(|e| format!("读取便签文件失败: {}", e))?
+        .len();
+    if file_size <= max_bytes {
+        let content = fs::read_to_string(file_path)
+            .map_err(|e| format!("读取便签文件失败: {}", e))?;
+        return Ok((content, false));
+    }
+
+    let mut buffer = Vec::new();
+    file.take(max_bytes).read_to_end(&mut buffer)
+        .map_err(|e| format!("读取便签文件失败: {}", e))?;
+    let valid_len = match std::str::from_utf8(&buffer) {
+        Ok(_) => buffer.len(),
+        Err(e) => e.valid_up_to(),
+    };
+    buffer.truncate(valid_len);
+    let content = String::from_utf8(buffer)
+        .map_err(|e| format!("读取便签文件失败: {}", e))?;
+    Ok((content, true))
+}
+
+// 保存前检查正文大小是否超过设置中的上限
+fn check_note_size(content: &str, settings: &ScheduleSettings) -> Result<(), String> {
+    let max_bytes = settings.max_note_bytes;
+    if content.len() as u64 > max_bytes {
+        return Err(format!("便签内容超过大小上限（{} 字节）", max_bytes));
+    }
+    Ok(())
+}
+
+// 把便签正文渲染成只读预览用的 HTML（按 peek 方式读取，不刷新活跃时间、不回写文件）
+// 关闭 Markdown 的便签按纯文本转义输出
+#[tauri::command]
+async fn render_note_html(window: tauri::WebviewWindow, id: String, unlock_token: Option<String>) -> Result<String, String> {
+    let loaded = load_note(window.clone(), id.clone(), unlock_token, Some(true)).await?
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    if loaded.locked {
+        return Err("便签已锁定，请先解锁".to_string());
+    }
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let markdown = read_index(&notes_dir)?.notes.iter()
+        .find(|note| note.id == id)
+        .is_none_or(|note| note.markdown);
+
+    Ok(if markdown {
+        render_markdown_html(&loaded.content)
+    } else {
+        render_plaintext_html(&loaded.content)
+    })
+}
+
+#[tauri::command]
+// peek 为 true 时允许读取归档便签（只读预览窗口使用），且不触发 extendOnRead
+async fn load_note(window: tauri::WebviewWindow, id: String, unlock_token: Option<String>, peek: Option<bool>) -> Result<Option<LoadedNote>, String> {
+    let peek = peek.unwrap_or(false);
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    
+    if !notes_dir.join("index.json").exists() {
+        return Ok(None);
+    }
+    let mut index = read_index(&notes_dir)?;
+
+    // 在索引中查找该ID的便签
+    let note = index.notes.iter_mut().find(|note| note.id == id);
+    
+    if let Some(entry) = note {
+        if !is_active(entry) && !peek {
+            return Ok(None);
+        }
+        let session = unlocked_session(&app_handle, &id, unlock_token.as_deref());
+        let locked_note = LoadedNote {
+            content: String::new(),
+            truncated: false,
+            locked: true,
+        };
+        if entry.protected.is_some() && session.is_none() {
+            return Ok(Some(locked_note));
+        }
+        let file_path = notes_dir.join(&entry.file.relative_path);
+        if file_path.exists() {
+            let settings = load_schedule_settings_from_disk();
+            let (full_content, truncated) = read_note_file_limited(&file_path, settings.max_note_bytes)?;
+            let mut pure_content = extract_content_only(&full_content);
+            if is_encrypted(entry) {
+                if entry.encryption == BodyEncryption::Note && session.as_ref().and_then(|session| session.body_key).is_none() {
+                    return Ok(Some(locked_note));
+                }
+                if truncated {
+                    return Err("加密便签超过大小上限，无法解密".to_string());
+                }
+                pure_content = decrypt_entry_body(&app_handle, entry, pure_content, session.as_ref())?;
+            }
+
+            // 开启 extendOnRead 时，读取也像编辑一样刷新活跃时间和过期时间（按 fadeAnchor 计算）
+            if settings.extend_on_read && !peek {
+                let now = Local::now();
+                entry.last_active_at = now.to_rfc3339();
+                entry.expire_at = Some(renewed_expire_at(entry, &now));
+                save_index(&notes_dir, &mut index)?;
+                emit_index_changed(&app_handle, "updated", vec![id.clone()]);
+            }
+            Ok(Some(LoadedNote {
+                content: pure_content,
+                truncated,
+                locked: false,
+            }))
+        } else {
+            Ok(None)
+        }
+    } else {
+        Ok(None)
+    }
+}
+
+// 取出有效的解锁会话：令牌必须与该便签最近一次解锁发放的一致且未超时，超时的会话顺带清除
+fn unlocked_session(app_handle: &tauri::AppHandle, id: &str, unlock_token: Option<&str>) -> Option<UnlockedNote> {
+    let unlock_token = unlock_token?;
+    let app_state = app_handle.state::<AppState>();
+    let mut unlocked_notes = app_state.unlocked_notes.lock().unwrap();
+    match unlocked_notes.get(id) {
+        Some(session) if session.unlocked_at.elapsed() <= UNLOCK_TIMEOUT => {
+            (session.token == unlock_token).then(|| session.clone())
+        }
+        Some(_) => {
+            unlocked_notes.remove(id);
+            None
+        }
+        None => None,
+    }
+}
+
+// 全局正文加密的盐；未开启或设置中的盐无效时返回 None
+fn global_encryption_salt(settings: &ScheduleSettings) -> Option<Vec<u8>> {
+    salt_from_hex(settings.encryption_salt.as_deref()?)
+}
+
+// 便签正文是否加密保存：只看索引中的 encryption 标记，正文恰好以加密前缀开头的明文便签不受影响
+fn is_encrypted(entry: &NoteEntry) -> bool {
+    entry.encryption != BodyEncryption::None
+}
+
+// 正文是否不能出现在预览、快速切换、归档列表和导出中：加密或受口令保护（即使未加密）的便签都不能
+fn hides_body(entry: &NoteEntry) -> bool {
+    is_encrypted(entry) || entry.protected.is_some()
+}
+
+// 旧索引没有 encryption 标记时，按正文能否解析出密文结构补齐：受保护便签的密文为单独加密，
+// 盐与全局加密盐一致的为全局加密；其余一律视为明文。只用于补齐缺失的标记和扫描到的新文件
+fn detect_legacy_encryption(body: &str, protected: bool, global_salt: Option<&[u8]>) -> BodyEncryption {
+    match encrypted_body_salt(body) {
+        Some(salt) if global_salt == Some(salt.as_slice()) => BodyEncryption::Global,
+        Some(_) if protected => BodyEncryption::Note,
+        _ => BodyEncryption::None,
+    }
+}
+
+// 扫描到的文件对应的加密标记和口令哈希：已有条目原样沿用，新文件按正文补齐（新文件没有口令哈希，只可能是全局加密）
+fn scanned_encryption(body: &str, existing: Option<&NoteEntry>, settings: &ScheduleSettings) -> (BodyEncryption, Option<String>) {
+    match existing {
+        Some(entry) => (entry.encryption, entry.protected.clone()),
+        None => (detect_legacy_encryption(body, false, global_encryption_salt(settings).as_deref()), None),
+    }
+}
+
+fn global_encryption_key(app_handle: &tauri::AppHandle) -> Result<[u8; 32], String> {
+    (*app_handle.state::<AppState>().encryption_key.lock().unwrap())
+        .ok_or_else(|| "便签内容已加密，请先输入加密口令".to_string())
+}
+
+// 用全局密钥解密；解不开说明当前口令与加密时的口令不一致，返回单独的错误而不是乱码
+fn decrypt_global_body(app_handle: &tauri::AppHandle, body: &str) -> Result<String, String> {
+    decrypt_body(&global_encryption_key(app_handle)?, body)
+        .map_err(|_| "加密口令错误，无法解密便签".to_string())
+}
+
+// 按索引中的加密标记取出明文正文；单独加密的便签需要带密钥的解锁会话
+fn decrypt_entry_body(app_handle: &tauri::AppHandle, entry: &NoteEntry, body: String, session: Option<&UnlockedNote>) -> Result<String, String> {
+    match entry.encryption {
+        BodyEncryption::None => Ok(body),
+        BodyEncryption::Global => decrypt_global_body(app_handle, &body),
+        BodyEncryption::Note => {
+            let body_key = session.and_then(|session| session.body_key)
+                .ok_or_else(|| "便签已锁定，请先解锁".to_string())?;
+            decrypt_body(&body_key, &body)
+        }
+    }
+}
+
+// 开启全局加密时用全局密钥加密正文，否则原样返回
+fn encrypt_if_global(app_handle: &tauri::AppHandle, body: String, settings: &ScheduleSettings) -> Result<String, String> {
+    match global_encryption_salt(settings) {
+        Some(salt) => encrypt_body(&global_encryption_key(app_handle)?, &salt, &body),
+        None => Ok(body),
+    }
+}
+
+// 生成写入文件的正文、对应的预览和新的加密标记：单独加密的便签用解锁时派生的密钥重新加密（沿用原来的盐），预览留空
+// 开启全局加密后，未单独加密的便签统一用全局密钥加密
+fn body_for_write(app_handle: &tauri::AppHandle, entry: &NoteEntry, existing_content: &str, content: &str, unlock_token: Option<&str>, settings: &ScheduleSettings) -> Result<(String, Option<String>, BodyEncryption), String> {
+    if entry.encryption == BodyEncryption::Note {
+        let body_key = unlocked_session(app_handle, &entry.id, unlock_token)
+            .and_then(|session| session.body_key)
+            .ok_or_else(|| "便签已锁定，无法保存".to_string())?;
+        let salt = encrypted_body_salt(&extract_content_only(existing_content))
+            .ok_or_else(|| "加密内容格式无效".to_string())?;
+        return Ok((encrypt_body(&body_key, &salt, content)?, None, BodyEncryption::Note));
+    }
+    let (body, preview, encryption) = plain_body_for_write(app_handle, content, settings)?;
+    Ok((body, preview.filter(|_| entry.protected.is_none()), encryption))
+}
+
+// 保存的正文与文件中已有的（解密后）正文一致，且按当前格式重新生成的 Front Matter 与文件一致时无需重写文件
+fn note_file_unchanged(existing_content: &str, existing_plain: Option<&str>, content: &str, id: &str, created_at: &str, style: FrontMatterStyle) -> bool {
+    existing_plain == Some(content)
+        && build_full_content(id, created_at, &extract_content_only(existing_content), style) == existing_content
+}
+
+// 未单独加密的正文：开启全局加密时加密写入且不生成预览，否则原样写入
+fn plain_body_for_write(app_handle: &tauri::AppHandle, content: &str, settings: &ScheduleSettings) -> Result<(String, Option<String>, BodyEncryption), String> {
+    if global_encryption_salt(settings).is_some() {
+        return Ok((encrypt_if_global(app_handle, content.to_string(), settings)?, None, BodyEncryption::Global));
+    }
+    Ok((content.to_string(), note_preview(content, settings), BodyEncryption::None))
+}
+
+// 全局正文加密（Front Matter 和索引保持明文），口令本身不落盘：
+// - 未开启时传入口令：生成盐和校验串写入 settings.json，并立即加密所有明文正文
+// - 已开启时传入口令：按校验串核对后把密钥保存在内存中（每次启动后需要输入一次）
+// - 传入 None：用内存中的密钥把全局加密的正文解密回明文，再清除设置中的盐和校验串
+// 单独设置了口令加密的便签不受影响
+#[tauri::command]
+async fn set_encryption_passphrase(window: tauri::WebviewWindow, passphrase: Option<String>) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut settings = load_schedule_settings_from_disk();
+    let stored = settings.encryption_salt.as_deref().and_then(salt_from_hex)
+        .zip(settings.encryption_verifier.clone());
+
+    match (passphrase, stored) {
+        (Some(passphrase), Some((salt, verifier))) => {
+            let key = derive_body_key(&passphrase, &salt)?;
+            if !check_passphrase_verifier(&key, &verifier) {
+                warn!("加密口令校验失败");
+                return Err("口令错误".to_string());
+            }
+            *app_handle.state::<AppState>().encryption_key.lock().unwrap() = Some(key);
+            Ok(())
+        }
+        (Some(passphrase), None) => {
+            if passphrase.is_empty() {
+                return Err("口令不能为空".to_string());
+            }
+            let salt = new_encryption_salt();
+            let key = derive_body_key(&passphrase, &salt)?;
+            settings.encryption_salt = Some(salt_to_hex(&salt));
+            settings.encryption_verifier = Some(new_passphrase_verifier(&key, &salt)?);
+            // 先保存设置再加密文件：中途失败时剩下的明文便签会在下次保存时加密
+            save_schedule_settings_to_disk(&settings)?;
+            *app_handle.state::<AppState>().encryption_key.lock().unwrap() = Some(key);
+
+            let updated_ids = rewrite_note_bodies(&notes_dir, |entry, body| {
+                if is_encrypted(entry) {
+                    return Ok(None);
+                }
+                encrypt_body(&key, &salt, body).map(|body| Some((body, BodyEncryption::Global)))
+            })?;
+            info!(count = updated_ids.len(), "已开启全局正文加密");
+            emit_index_changed(&app_handle, "updated", updated_ids);
+            Ok(())
+        }
+        (None, Some(_)) => {
+            let key = global_encryption_key(&app_handle)?;
+            // 先解密文件再清除设置：中途失败时仍可用同一口令继续
+            let updated_ids = rewrite_note_bodies(&notes_dir, |entry, body| {
+                if entry.encryption != BodyEncryption::Global {
+                    return Ok(None);
+                }
+                decrypt_body(&key, body)
+                    .map(|body| Some((body, BodyEncryption::None)))
+                    .map_err(|_| "加密口令错误，无法解密便签".to_string())
+            })?;
+            settings.encryption_salt = None;
+            settings.encryption_verifier = None;
+            save_schedule_settings_to_disk(&settings)?;
+            *app_handle.state::<AppState>().encryption_key.lock().unwrap() = None;
+            info!(count = updated_ids.len(), "已关闭全局正文加密");
+            emit_index_changed(&app_handle, "updated", updated_ids);
+            Ok(())
+        }
+        (None, None) => Ok(()),
+    }
+}
+
+// 按 transform 改写每个便签文件的正文和加密标记（返回 None 表示不改），保留 Front Matter 并同步 cachedPreview
+// 索引在最后统一写入；中途中断时，已用全局盐加密的文件会在下次启动补齐标记时识别出来。返回被改写的便签ID
+fn rewrite_note_bodies(notes_dir: &Path, transform: impl Fn(&NoteEntry, &str) -> Result<Option<(String, BodyEncryption)>, String>) -> Result<Vec<String>, String> {
+    let settings = load_schedule_settings_from_disk();
+    let mut index = read_index(notes_dir)?;
+    let mut updated_ids = Vec::new();
+    for entry in &mut index.notes {
+        let file_path = notes_dir.join(&entry.file.relative_path);
+        let content = match fs::read_to_string(&file_path) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!(note_id = %entry.id, error = %e, "读取便签文件失败，跳过");
+                continue;
+            }
+        };
+        let body = extract_content_only(&content);
+        let (new_body, encryption) = match transform(entry, &body)? {
+            Some(rewritten) => rewritten,
+            None => continue,
+        };
+        let existing_id = parse_id_from_content(&content)
+            .unwrap_or_else(|| entry.id.clone());
+        let created_at = extract_created_at_from_content(&content)
+            .unwrap_or_else(|| entry.created_at.clone());
+        write_file_safely(&file_path, build_full_content(&existing_id, &created_at, &new_body, front_matter_style(&settings)))
+            .map_err(|e| format!("写入便签文件失败: {}", e))?;
+        entry.encryption = encryption;
+        entry.cached_preview = if hides_body(entry) { None } else { note_preview(&new_body, &settings) };
+        updated_ids.push(entry.id.clone());
+    }
+    save_index(notes_dir, &mut index)?;
+    Ok(updated_ids)
+}
+
+// 设置或清除便签口令：索引中只保存加盐的 Argon2 哈希；passphrase 为 None 时取消保护
+// encrypt 为 true 时正文用口令派生的密钥加密保存，Front Matter 保持明文；取消保护或不加密时正文恢复为明文
+// 已受保护的便签需要提供有效的解锁令牌才能修改；新正文全部生成成功后才写入文件
+#[tauri::command]
+async fn set_note_protected(window: tauri::WebviewWindow, id: String, passphrase: Option<String>, unlock_token: Option<String>, encrypt: Option<bool>) -> Result<(), String> {
+    let settings = load_schedule_settings_from_disk();
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    let entry = index.notes.iter_mut().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    let session = match entry.protected {
+        Some(_) => Some(unlocked_session(&app_handle, &id, unlock_token.as_deref())
+            .ok_or_else(|| "便签已锁定，请先解锁".to_string())?),
+        None => None,
+    };
+    if passphrase.as_deref() == Some("") {
+        return Err("口令不能为空".to_string());
+    }
+    let encrypt = encrypt.unwrap_or(false);
+    if encrypt && passphrase.is_none() {
+        return Err("加密便签需要设置口令".to_string());
+    }
+    let protected = passphrase.as_deref().map(hash_passphrase).transpose()?;
+
+    let file_path = notes_dir.join(&entry.file.relative_path);
+    let existing_content = fs::read_to_string(&file_path)
+        .map_err(|e| format!("读取便签文件失败: {}", e))?;
+    let existing_body = extract_content_only(&existing_content);
+    let plain_body = match entry.encryption {
+        BodyEncryption::Global => decrypt_global_body(&app_handle, &existing_body)?,
+        BodyEncryption::Note => {
+            let body_key = session.as_ref()
+                .and_then(|session| session.body_key)
+                .ok_or_else(|| "缺少解密密钥，请重新解锁".to_string())?;
+            decrypt_body(&body_key, &existing_body)?
+        }
+        BodyEncryption::None => existing_body.clone(),
+    };
+    let (new_body, encryption) = match passphrase.as_deref() {
+        Some(passphrase) if encrypt => {
+            let salt = new_encryption_salt();
+            (encrypt_body(&derive_body_key(passphrase, &salt)?, &salt, &plain_body)?, BodyEncryption::Note)
+        }
+        _ if global_encryption_salt(&settings).is_some() => (encrypt_if_global(&app_handle, plain_body, &settings)?, BodyEncryption::Global),
+        _ => (plain_body, BodyEncryption::None),
+    };
+
+    if new_body != existing_body {
+        let existing_id = parse_id_from_content(&existing_content)
+            .unwrap_or_else(|| entry.id.clone());
+        let created_at = extract_created_at_from_content(&existing_content)
+            .unwrap_or_else(|| entry.created_at.clone());
+        write_file_safely(&file_path, build_full_content(&existing_id, &created_at, &new_body, front_matter_style(&settings)))
+            .map_err(|e| format!("写入便签文件失败: {}", e))?;
+    }
+    entry.encryption = encryption;
+    entry.protected = protected;
+    entry.cached_preview = if hides_body(entry) { None } else { note_preview(&new_body, &settings) };
+    save_index(&notes_dir, &mut index)?;
+
+    // 修改口令后之前发放的令牌作废
+    app_handle.state::<AppState>().unlocked_notes.lock().unwrap().remove(&id);
+    emit_index_changed(&app_handle, "updated", vec![id]);
+    Ok(())
+}
+
+// 校验口令并发放短期有效的解锁令牌，供 load_note / save_note_content 读写正文时使用
+// 正文已加密时同时派生并试解密一次，口令对不上密文时直接失败，不会改动文件
+#[tauri::command]
+async fn unlock_note(window: tauri::WebviewWindow, id: String, passphrase: String) -> Result<String, String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+
+    let entry = index.notes.iter().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    let stored_hash = entry.protected.as_deref()
+        .ok_or_else(|| "该便签未受保护".to_string())?;
+    if !verify_passphrase(&passphrase, stored_hash) {
+        warn!(note_id = %id, "便签口令校验失败");
+        return Err("口令错误".to_string());
+    }
+
+    let content = fs::read_to_string(notes_dir.join(&entry.file.relative_path))
+        .map_err(|e| format!("读取便签文件失败: {}", e))?;
+    let body = extract_content_only(&content);
+    let body_key = if entry.encryption == BodyEncryption::Note {
+        let salt = encrypted_body_salt(&body)
+            .ok_or_else(|| "加密内容格式无效".to_string())?;
+        let body_key = derive_body_key(&passphrase, &salt)?;
+        decrypt_body(&body_key, &body)?;
+        Some(body_key)
+    } else {
+        None
+    };
+
+    let token = Uuid::new_v4().to_string();
+    app_handle.state::<AppState>().unlocked_notes.lock().unwrap().insert(id, UnlockedNote {
+        token: token.clone(),
+        unlocked_at: std::time::Instant::now(),
+        body_key,
+    });
+    Ok(token)
+}
+
+// 返回去掉 Front Matter 和 Markdown 标记的纯文本，供朗读/无障碍使用；活跃和已归档的便签都可以
+// 受保护的便签需要有效的解锁令牌
+#[tauri::command]
+async fn get_note_plaintext(window: tauri::WebviewWindow, id: String, unlock_token: Option<String>) -> Result<String, String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+
+    let entry = index.notes.iter().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    let session = unlocked_session(&app_handle, &id, unlock_token.as_deref());
+    if entry.protected.is_some() && session.is_none() {
+        return Err("便签已锁定，请先解锁".to_string());
+    }
+    let content = fs::read_to_string(notes_dir.join(&entry.file.relative_path))
+        .map_err(|e| format!("读取便签文件失败: {}", e))?;
+    let body = decrypt_entry_body(&app_handle, entry, extract_content_only(&content), session.as_ref())?;
+    if !entry.markdown {
+        return Ok(body.trim().to_string());
+    }
+    Ok(markdown_to_plaintext(&body))
+}
+
+// 便签正文（不含 Front Matter）的 SHA-256 指纹，供外部同步工具检测内容变化
+// 加密便签返回密文的指纹，内容变化时同样会改变
+#[tauri::command]
+async fn note_content_hash(window: tauri::WebviewWindow, id: String) -> Result<String, String> {
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+
+    let entry = index.notes.iter().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    let content = fs::read_to_string(notes_dir.join(&entry.file.relative_path))
+        .map_err(|e| format!("读取便签文件失败: {}", e))?;
+    Ok(body_sha256(&extract_content_only(&content)))
+}
+
+// 更新便签的活动时间
+#[tauri::command]
+async fn update_note_activity(window: tauri::WebviewWindow, id: String) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    // 查找并更新指定ID的便签
+    if let Some(entry) = index.notes.iter_mut().find(|note| note.id == id) {
+        if !is_active(entry) {
+            return Err("note archived".to_string());
+        }
+        // 更新last_active_at和expire_at
+        let now = Local::now();
+        entry.last_active_at = now.to_rfc3339();
+        entry.expire_at = Some(renewed_expire_at(entry, &now));
+
+        // 保存更新后的索引
+        save_index(&notes_dir, &mut index)?;
+        emit_index_changed(&app_handle, "updated", vec![id.clone()]);
+
+        Ok(())
+    } else {
+        Err("找不到指定的便签".to_string())
+    }
+}
+
+// 不编辑内容、像编辑一样按 fadeAnchor 重新计算过期时间，返回新的过期时间；已归档的便签报错
+#[tauri::command]
+async fn touch_note(window: tauri::WebviewWindow, id: String) -> Result<String, String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    let entry = index.notes.iter_mut().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    if !is_active(entry) {
+        return Err("便签已被归档，无法更新".to_string());
+    }
+    let now = Local::now();
+    let expire_at = renewed_expire_at(entry, &now);
+    entry.last_active_at = now.to_rfc3339();
+    entry.expire_at = Some(expire_at.clone());
+    save_index(&notes_dir, &mut index)?;
+
+    emit_index_changed(&app_handle, "updated", vec![id]);
+    Ok(expire_at)
+}
+
+// 恢复便签 - 统一入口
+fn internal_restore_note(entry: &mut NoteEntry, now: &DateTime<Local>) {
+    entry.archived_at = None;
+    entry.last_active_at = now.to_rfc3339();
+    record_note_event(entry, "restored", &now.to_rfc3339());
+    let new_expire_time = now.with_timezone(&chrono::Utc) + Duration::days(7);
+    entry.expire_at = Some(new_expire_time.to_rfc3339());
+}
+
+// 设置便签过期时间的计算起点，活跃便签立即按新的起点重新计算过期时间
+#[tauri::command]
+async fn set_note_fade_anchor(window: tauri::WebviewWindow, id: String, anchor: FadeAnchor) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    let entry = index.notes.iter_mut().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    entry.fade_anchor = anchor;
+    if is_active(entry) {
+        let last_active = DateTime::parse_from_rfc3339(&entry.last_active_at)
+            .map(|time| time.with_timezone(&Local))
+            .unwrap_or_else(|_| Local::now());
+        entry.expire_at = Some(renewed_expire_at(entry, &last_active));
+    }
+    save_index(&notes_dir, &mut index)?;
+
+    emit_index_changed(&app_handle, "updated", vec![id]);
+    Ok(())
+}
+
+// 设置便签是否永不淡出；和固定一样不修改 lastActiveAt / expireAt，取消后按原有的过期时间继续淡出
+#[tauri::command]
+async fn set_note_never_expire(window: tauri::WebviewWindow, id: String, never_expire: bool) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    let entry = index.notes.iter_mut().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    apply_note_never_expire(entry, never_expire);
+    save_index(&notes_dir, &mut index)?;
+
+    emit_index_changed(&app_handle, "updated", vec![id]);
+    Ok(())
+}
+
+// 设置启动时聚焦的便签；设为 true 时清除其他便签上的标记，保证只有一张
+#[tauri::command]
+async fn set_note_focus_on_launch(window: tauri::WebviewWindow, id: String, value: bool) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+    if !index.notes.iter().any(|note| note.id == id) {
+        return Err("找不到指定的便签".to_string());
+    }
+
+    let mut changed_ids = Vec::new();
+    for entry in &mut index.notes {
+        let focus_on_launch = if entry.id == id { value } else { entry.focus_on_launch && !value };
+        if entry.focus_on_launch != focus_on_launch {
+            entry.focus_on_launch = focus_on_launch;
+            changed_ids.push(entry.id.clone());
+        }
+    }
+    if changed_ids.is_empty() {
+        return Ok(());
+    }
+    save_index(&notes_dir, &mut index)?;
+
+    emit_index_changed(&app_handle, "updated", changed_ids);
+    Ok(())
+}
+
+// 设置便签固定状态
+#[tauri::command]
+async fn set_note_pinned(window: tauri::WebviewWindow, id: String, pinned: bool) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    // 固定状态属于元数据，不修改 lastActiveAt / expireAt：取消固定后按原有的过期时间继续淡出
+    let entry = index.notes.iter_mut().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    apply_note_pinned(entry, pinned);
+    // 手动设置后不再视为临时固定
+    app_handle.state::<AppState>().temporary_pins.lock().unwrap().remove(&id);
+    save_index(&notes_dir, &mut index)?;
+
+    emit_index_changed(&app_handle, "updated", vec![id]);
+    Ok(())
+}
+
+// 元数据设置：只修改对应字段，lastActiveAt / expireAt 保持原样（即使原过期时间已经过去）
+fn apply_note_pinned(entry: &mut NoteEntry, pinned: bool) {
+    entry.pinned = pinned;
+}
+
+fn apply_note_never_expire(entry: &mut NoteEntry, never_expire: bool) {
+    entry.never_expire = never_expire;
+}
+
+fn apply_note_tags(entry: &mut NoteEntry, tags: Vec<String>) {
+    entry.tags = normalize_tags(tags);
+}
+
+fn apply_note_colors(entry: &mut NoteEntry, colors: &NoteColors) {
+    entry.bg_color = colors.bg_color.clone();
+    entry.text_color = colors.text_color.clone();
+}
+
+// 从临时固定集合中取出到 now 为止已到期的便签（now 为 None 时取出全部），返回其ID
+fn take_temporary_pins(temporary_pins: &mut std::collections::HashMap<String, DateTime<Local>>, now: Option<&DateTime<Local>>) -> Vec<String> {
+    let mut taken_ids = Vec::new();
+    temporary_pins.retain(|id, until| {
+        let lapsed = now.is_none_or(|now| *until <= *now);
+        if lapsed {
+            taken_ids.push(id.clone());
+        }
+        !lapsed
+    });
+    taken_ids
+}
+
+// 取消临时固定：和 set_note_pinned(false) 一样只修改 pinned，返回实际取消固定的ID
+fn unpin_entries(index: &mut IndexFile, ids: &[String]) -> Vec<String> {
+    let mut unpinned_ids = Vec::new();
+    for entry in index.notes.iter_mut().filter(|entry| entry.pinned && ids.contains(&entry.id)) {
+        apply_note_pinned(entry, false);
+        unpinned_ids.push(entry.id.clone());
+    }
+    unpinned_ids
+}
+
+// 临时固定在 within_hours 小时内即将淡出的活跃便签，minutes_to_pin 分钟后由生命周期任务自动取消，返回被固定的ID
+#[tauri::command]
+async fn pin_expiring_notes(window: tauri::WebviewWindow, within_hours: u32, minutes_to_pin: u32) -> Result<Vec<String>, String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    let now = Local::now();
+    let deadline = now + Duration::hours(within_hours as i64);
+    let mut pinned_ids = Vec::new();
+    for entry in index.notes.iter_mut().filter(|entry| is_active(entry) && !entry.pinned) {
+        if is_expired_check(entry, &deadline) {
+            apply_note_pinned(entry, true);
+            pinned_ids.push(entry.id.clone());
+        }
+    }
+    if pinned_ids.is_empty() {
+        return Ok(pinned_ids);
+    }
+    save_index(&notes_dir, &mut index)?;
+
+    let pinned_until = now + Duration::minutes(minutes_to_pin as i64);
+    app_handle.state::<AppState>().temporary_pins.lock().unwrap()
+        .extend(pinned_ids.iter().map(|id| (id.clone(), pinned_until)));
+    emit_index_changed(&app_handle, "updated", pinned_ids.clone());
+    Ok(pinned_ids)
+}
+
+// 立即取消所有临时固定，返回被取消固定的ID
+#[tauri::command]
+async fn unpin_temporary(window: tauri::WebviewWindow) -> Result<Vec<String>, String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+    let temporary_ids = take_temporary_pins(&mut app_handle.state::<AppState>().temporary_pins.lock().unwrap(), None);
+    let reverted_ids = unpin_entries(&mut index, &temporary_ids);
+    if !reverted_ids.is_empty() {
+        save_index(&notes_dir, &mut index)?;
+        emit_index_changed(&app_handle, "updated", reverted_ids.clone());
+    }
+    Ok(reverted_ids)
+}
+
+// 设置便签提醒，at_iso 为 None 时清除；repeat_hours 为 None 表示只提醒一次
+#[tauri::command]
+async fn set_note_reminder(window: tauri::WebviewWindow, id: String, at_iso: Option<String>, repeat_hours: Option<u32>) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let reminder_at = match at_iso {
+        Some(at_iso) => Some(DateTime::parse_from_rfc3339(&at_iso)
+            .map_err(|e| format!("无效的提醒时间: {}", e))?
+            .to_rfc3339()),
+        None => None,
+    };
+    let mut index = read_index(&notes_dir)?;
+
+    let entry = index.notes.iter_mut().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    entry.reminder_repeat_hours = repeat_hours.filter(|_| reminder_at.is_some());
+    entry.reminder_at = reminder_at;
+    save_index(&notes_dir, &mut index)?;
+
+    emit_index_changed(&app_handle, "updated", vec![id]);
+    Ok(())
+}
+
+// 切换便签的 Markdown / 纯文本模式，已打开的窗口收到事件后切换编辑器渲染方式
+#[tauri::command]
+async fn set_note_markdown(window: tauri::WebviewWindow, id: String, markdown: bool) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
 
-    let mut index: IndexFile = {
-        let content = fs::read_to_string(&index_path)
-            .map_err(|e| format!("读取索引文件失败: {}", e))?;
-        serde_json::from_str(&content)
-            .map_err(|e| format!("解析索引文件失败: {}", e))?
+    let entry = index.notes.iter_mut().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    entry.markdown = markdown;
+    save_index(&notes_dir, &mut index)?;
+    emit_index_changed(&app_handle, "updated", vec![id.clone()]);
+
+    let label = format!("note-{}", id);
+    if app_handle.get_webview_window(&label).is_some() {
+        let _ = app_handle.emit_to(label.as_str(), "fadenote://markdown-mode-changed", markdown);
+    }
+    Ok(())
+}
+
+// 校验并规范化十六进制颜色（#RGB、#RRGGBB、#RRGGBBAA），空字符串视为未设置
+fn normalize_hex_color(color: Option<String>) -> Result<Option<String>, String> {
+    let color = match color.as_deref().map(str::trim) {
+        None | Some("") => return Ok(None),
+        Some(color) => color,
     };
+    let digits = color.strip_prefix('#').unwrap_or("");
+    if ![3, 6, 8].contains(&digits.len()) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("无效的颜色值: {}", color));
+    }
+    Ok(Some(format!("#{}", digits.to_ascii_lowercase())))
+}
 
-    // 查找并更新指定ID的便签
+// 设置便签窗口的背景色和文字颜色，None 表示恢复主题颜色；已打开的窗口立即应用
+#[tauri::command]
+async fn set_note_colors(window: tauri::WebviewWindow, id: String, bg_color: Option<String>, text_color: Option<String>) -> Result<(), String> {
+    let colors = NoteColors {
+        bg_color: normalize_hex_color(bg_color)?,
+        text_color: normalize_hex_color(text_color)?,
+    };
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    let entry = index.notes.iter_mut().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    apply_note_colors(entry, &colors);
+    save_index(&notes_dir, &mut index)?;
+    emit_index_changed(&app_handle, "updated", vec![id.clone()]);
+
+    let label = format!("note-{}", id);
+    if app_handle.get_webview_window(&label).is_some() {
+        let _ = app_handle.emit_to(label.as_str(), "fadenote://colors-changed", colors);
+    }
+    Ok(())
+}
+
+// 设置便签字号，None 表示使用前端的基础字号
+#[tauri::command]
+async fn set_note_font_size(window: tauri::WebviewWindow, id: String, font_size: Option<u32>) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    let entry = index.notes.iter_mut().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    entry.font_size = font_size;
+    save_index(&notes_dir, &mut index)?;
+    emit_index_changed(&app_handle, "updated", vec![id.clone()]);
+
+    let label = format!("note-{}", id);
+    if app_handle.get_webview_window(&label).is_some() {
+        let _ = app_handle.emit_to(label.as_str(), "fadenote://font-size-changed", font_size);
+    }
+    Ok(())
+}
+
+// 切换便签窗口是否显示系统标题栏，已打开的窗口直接重新配置
+#[tauri::command]
+async fn set_note_decorated(window: tauri::WebviewWindow, id: String, decorated: bool) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let default_window_info = default_note_window_info(&notes_dir);
+    let mut index = read_index(&notes_dir)?;
+
+    let entry = index.notes.iter_mut().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    entry.window.get_or_insert(default_window_info).decorated = decorated;
+    save_index(&notes_dir, &mut index)?;
+    emit_index_changed(&app_handle, "updated", vec![id.clone()]);
+
+    if let Some(note_window) = app_handle.get_webview_window(&format!("note-{}", id)) {
+        note_window.set_decorations(decorated)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// 绑定显示器的坐标原点（该显示器左上角）；显示器已不存在时回退到主显示器
+fn monitor_origin(app_handle: &tauri::AppHandle, monitor_index: usize) -> Option<(f64, f64)> {
+    let monitor = match app_handle.available_monitors().ok()?.into_iter().nth(monitor_index) {
+        Some(monitor) => monitor,
+        None => app_handle.primary_monitor().ok()??,
+    };
+    let position = monitor.position();
+    Some((position.x as f64, position.y as f64))
+}
+
+// 显示器工作区（去掉任务栏 / 菜单栏）的逻辑坐标：(x, y, width, height)
+fn monitor_work_area(monitor: &tauri::Monitor) -> (f64, f64, f64, f64) {
+    let scale_factor = monitor.scale_factor();
+    let work_area = monitor.work_area();
+    let position = work_area.position.to_logical::<f64>(scale_factor);
+    let size = work_area.size.to_logical::<f64>(scale_factor);
+    (position.x, position.y, size.width, size.height)
+}
+
+// 把窗口左上角限制在工作区内，窗口比工作区大时贴住工作区左上角
+fn clamp_to_work_area(area: (f64, f64, f64, f64), x: f64, y: f64, width: f64, height: f64) -> (f64, f64) {
+    let (area_x, area_y, area_width, area_height) = area;
+    let x = x.min(area_x + area_width - width).max(area_x);
+    let y = y.min(area_y + area_height - height).max(area_y);
+    (x.round(), y.round())
+}
+
+// 还原的窗口与任何显示器的工作区都不相交时（例如外接显示器已拔掉），挪回主显示器工作区内
+fn clamp_to_visible_monitor(app_handle: &tauri::AppHandle, x: f64, y: f64, width: f64, height: f64) -> (f64, f64) {
+    let monitors = app_handle.available_monitors().unwrap_or_default();
+    let visible = monitors.iter().map(monitor_work_area).any(|(area_x, area_y, area_width, area_height)| {
+        x < area_x + area_width && x + width > area_x && y < area_y + area_height && y + height > area_y
+    });
+    if visible || monitors.is_empty() {
+        return (x, y);
+    }
+    match app_handle.primary_monitor().ok().flatten() {
+        Some(monitor) => clamp_to_work_area(monitor_work_area(&monitor), x, y, width, height),
+        None => (x, y),
+    }
+}
+
+// 全局坐标 -> 索引中保存的坐标：绑定显示器时相对该显示器，否则原样保存
+fn to_stored_position(app_handle: &tauri::AppHandle, monitor_index: Option<usize>, x: f64, y: f64) -> (f64, f64) {
+    match monitor_index.and_then(|monitor_index| monitor_origin(app_handle, monitor_index)) {
+        Some((origin_x, origin_y)) => (x - origin_x, y - origin_y),
+        None => (x, y),
+    }
+}
+
+// 索引中保存的坐标 -> 全局坐标
+fn to_global_position(app_handle: &tauri::AppHandle, monitor_index: Option<usize>, x: f64, y: f64) -> (f64, f64) {
+    match monitor_index.and_then(|monitor_index| monitor_origin(app_handle, monitor_index)) {
+        Some((origin_x, origin_y)) => (x + origin_x, y + origin_y),
+        None => (x, y),
+    }
+}
+
+// 把便签绑定到指定显示器（None 取消绑定），已打开的窗口移动到该显示器上
+#[tauri::command]
+async fn set_note_monitor(window: tauri::WebviewWindow, id: String, monitor_index: Option<usize>) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    if let Some(monitor_index) = monitor_index {
+        let monitor_count = app_handle.available_monitors().map_err(|e| e.to_string())?.len();
+        if monitor_index >= monitor_count {
+            return Err(format!("显示器序号超出范围（共 {} 个显示器）", monitor_count));
+        }
+    }
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let default_window_info = default_note_window_info(&notes_dir);
+    let mut index = read_index(&notes_dir)?;
+
+    let entry = index.notes.iter_mut().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    let window_info = entry.window.get_or_insert(default_window_info);
+
+    // 先换算回全局坐标，再按新的绑定换算；切换到新显示器时保持相对位置
+    let (global_x, global_y) = to_global_position(&app_handle, window_info.monitor_index, window_info.x, window_info.y);
+    let (x, y) = match (window_info.monitor_index, monitor_index) {
+        (Some(_), Some(_)) => (window_info.x, window_info.y),
+        _ => to_stored_position(&app_handle, monitor_index, global_x, global_y),
+    };
+    // 原位置在目标显示器左上方之外时，放到该显示器上的默认位置
+    let (x, y) = if monitor_index.is_some() && (x < 0.0 || y < 0.0) {
+        (DEFAULT_NOTE_X, DEFAULT_NOTE_Y)
+    } else {
+        (x, y)
+    };
+    window_info.monitor_index = monitor_index;
+    window_info.x = x;
+    window_info.y = y;
+    let (target_x, target_y) = to_global_position(&app_handle, monitor_index, x, y);
+    save_index(&notes_dir, &mut index)?;
+    emit_index_changed(&app_handle, "updated", vec![id.clone()]);
+
+    if let Some(note_window) = app_handle.get_webview_window(&format!("note-{}", id)) {
+        note_window.set_position(tauri::PhysicalPosition::new(target_x as i32, target_y as i32))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// 把便签窗口移到指定显示器的工作区内：保持相对原显示器工作区的偏移，再限制在目标工作区内，
+// 新坐标按 update_note_window 的逻辑保存；已绑定显示器的便签同时改绑到目标显示器
+#[tauri::command]
+async fn move_note_to_monitor(window: tauri::WebviewWindow, id: String, monitor_index: usize) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let monitors = app_handle.available_monitors().map_err(|e| e.to_string())?;
+    let target_area = monitors.get(monitor_index)
+        .map(monitor_work_area)
+        .ok_or_else(|| format!("显示器序号超出范围（共 {} 个显示器）", monitors.len()))?;
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+    let entry = index.notes.iter_mut().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+
+    // 放大状态下按基础尺寸保存
+    let base_size = app_handle.state::<AppState>().zoomed_notes.lock().unwrap().get(&id).copied();
+    let note_window = app_handle.get_webview_window(&format!("note-{}", id));
+    let (x, y, width, height) = match (note_window.as_ref(), entry.window.as_ref()) {
+        (Some(note_window), _) => {
+            let scale_factor = note_window.scale_factor().map_err(|e| e.to_string())?;
+            let position = note_window.outer_position().map_err(|e| e.to_string())?.to_logical::<f64>(scale_factor);
+            let size = note_window.inner_size().map_err(|e| e.to_string())?.to_logical::<f64>(scale_factor);
+            (position.x, position.y, size.width, size.height)
+        }
+        (None, Some(window_info)) => {
+            let (x, y) = to_global_position(&app_handle, window_info.monitor_index, window_info.x, window_info.y);
+            (x, y, window_info.width, window_info.height)
+        }
+        (None, None) => {
+            let (x, y) = default_window_position(&app_handle, &id);
+            (x, y, DEFAULT_NOTE_WIDTH, DEFAULT_NOTE_HEIGHT)
+        }
+    };
+
+    // 以窗口当前所在的显示器为原显示器，找不到时按主显示器计算偏移
+    let source_area = monitors.iter().map(monitor_work_area)
+        .find(|(area_x, area_y, area_width, area_height)| {
+            x >= *area_x && x < area_x + area_width && y >= *area_y && y < area_y + area_height
+        })
+        .or_else(|| app_handle.primary_monitor().ok().flatten().map(|monitor| monitor_work_area(&monitor)))
+        .unwrap_or(target_area);
+    let (target_x, target_y) = clamp_to_work_area(
+        target_area,
+        target_area.0 + (x - source_area.0),
+        target_area.1 + (y - source_area.1),
+        width,
+        height,
+    );
+
+    if let Some(window_info) = entry.window.as_mut().filter(|window_info| window_info.monitor_index.is_some()) {
+        window_info.monitor_index = Some(monitor_index);
+        save_index(&notes_dir, &mut index)?;
+    }
+    if let Some(note_window) = note_window {
+        note_window.set_position(tauri::LogicalPosition::new(target_x, target_y))
+            .map_err(|e| e.to_string())?;
+    }
+    let (width, height) = base_size.unwrap_or((width, height));
+    update_note_window_info(&app_handle, &notes_dir, &id, target_x, target_y, width, height).await
+}
+
+// 当前平台是否支持“在所有虚拟桌面上显示”：Tauri 只在 macOS / Linux 上实现了该能力，
+// Windows 没有公开的 API 可以把窗口固定到所有虚拟桌面，因此在运行时按平台判断
+fn supports_all_desktops() -> bool {
+    cfg!(any(target_os = "macos", target_os = "linux"))
+}
+
+// 设置便签是否在所有虚拟桌面上显示，返回当前平台上是否实际生效（不支持时只保存设置）
+#[tauri::command]
+async fn set_note_on_all_desktops(window: tauri::WebviewWindow, id: String, on_all_desktops: bool) -> Result<bool, String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let default_window_info = default_note_window_info(&notes_dir);
+    let mut index = read_index(&notes_dir)?;
+
+    let entry = index.notes.iter_mut().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    entry.window.get_or_insert(default_window_info).on_all_desktops = on_all_desktops;
+    save_index(&notes_dir, &mut index)?;
+    emit_index_changed(&app_handle, "updated", vec![id.clone()]);
+
+    if !supports_all_desktops() {
+        return Ok(false);
+    }
+    if let Some(note_window) = app_handle.get_webview_window(&format!("note-{}", id)) {
+        note_window.set_visible_on_all_workspaces(on_all_desktops)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(true)
+}
+
+// 删除便签
+#[tauri::command]
+async fn delete_note(window: tauri::WebviewWindow, id: String) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    // 查找并删除指定ID的便签
+    if let Some(pos) = index.notes.iter().position(|note| note.id == id) {
+        let entry = &index.notes[pos];
+        
+        // 构造文件路径并删除文件
+        let file_path = notes_dir.join(&entry.file.relative_path);
+        // 删除前把文件内容留在内存中，供撤销使用
+        let file_bytes = fs::read(&file_path).ok();
+        if file_path.exists() {
+            fs::remove_file(&file_path)
+                .map_err(|e| format!("删除便签文件失败: {}", e))?;
+        }
+        
+        // 从索引中移除该便签
+        let prior = index.notes.remove(pos);
+        
+        // 保存更新后的索引
+        save_index(&notes_dir, &mut index)?;
+
+        record_undo_action(&app_handle, UndoKind::Deleted { prior, file_bytes });
+        emit_index_changed(&app_handle, "deleted", vec![id]);
+        Ok(())
+    } else {
+        Err("找不到指定的便签".to_string())
+    }
+}
+
+// 恢复归档的便签
+#[tauri::command]
+async fn restore_note(window: tauri::WebviewWindow, id: String) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    // 查找并恢复指定ID的便签
     if let Some(entry) = index.notes.iter_mut().find(|note| note.id == id) {
-        if !is_active(entry) {
-            return Err("note archived".to_string());
+        let prior = if entry.archived_at.is_some() {
+            let prior = entry.clone();
+            let now = Local::now();
+            internal_restore_note(entry, &now);
+            Some(prior)
+        } else {
+            None
+        };
+
+        // 保存更新后的索引
+        save_index(&notes_dir, &mut index)?;
+
+        if let Some(prior) = prior {
+            record_undo_action(&app_handle, UndoKind::Restored { prior });
+            emit_index_changed(&app_handle, "restored", vec![id]);
+        }
+        Ok(())
+    } else {
+        Err("找不到指定的便签".to_string())
+    }
+}
+
+// 在便签窗口中手动立即归档活跃便签，并关闭该便签窗口；已归档的便签返回错误
+#[tauri::command]
+async fn archive_note_now(window: tauri::WebviewWindow, id: String) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    let entry = index.notes.iter_mut().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    if !is_active(entry) {
+        return Err("该便签已归档".to_string());
+    }
+    let prior = entry.clone();
+    archive_note(entry, &Local::now())?;
+    derive_status(entry);
+    save_index(&notes_dir, &mut index)?;
+
+    record_undo_action(&app_handle, UndoKind::Archived { prior });
+    emit_index_changed(&app_handle, "archived", vec![id.clone()]);
+    // 直接销毁窗口，避免再次经过关闭请求中的隐藏 / 关闭时归档逻辑
+    if let Some(note_window) = app_handle.get_webview_window(&format!("note-{}", id)) {
+        note_window.destroy().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// 批量恢复 archivedAt 落在 [from, to] 区间内的归档便签，只写一次索引
+// archivedAt 无法解析的条目直接跳过
+#[tauri::command]
+async fn restore_notes_in_range(window: tauri::WebviewWindow, from_iso: String, to_iso: String) -> Result<Vec<String>, String> {
+    let from = DateTime::parse_from_rfc3339(&from_iso)
+        .map_err(|e| format!("解析时间失败: {}", e))?;
+    let to = DateTime::parse_from_rfc3339(&to_iso)
+        .map_err(|e| format!("解析时间失败: {}", e))?;
+
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    let now = Local::now();
+    let mut restored_ids = Vec::new();
+    for entry in index.notes.iter_mut() {
+        let archived_time = match entry.archived_at.as_deref().map(DateTime::parse_from_rfc3339) {
+            Some(Ok(archived_time)) => archived_time,
+            _ => continue,
+        };
+        if archived_time >= from && archived_time <= to {
+            internal_restore_note(entry, &now);
+            restored_ids.push(entry.id.clone());
+        }
+    }
+
+    if !restored_ids.is_empty() {
+        save_index(&notes_dir, &mut index)?;
+        emit_index_changed(&app_handle, "restored", restored_ids.clone());
+    }
+    Ok(restored_ids)
+}
+
+// 按创建时间区间 [from, to] 列出便签（只读索引），按创建时间排序；createdAt 无法解析的条目不返回
+#[tauri::command]
+async fn get_notes_in_created_range(window: tauri::WebviewWindow, from_iso: String, to_iso: String, include_archived: bool) -> Result<Vec<NoteEntry>, String> {
+    let from = DateTime::parse_from_rfc3339(&from_iso)
+        .map_err(|e| format!("解析时间失败: {}", e))?;
+    let to = DateTime::parse_from_rfc3339(&to_iso)
+        .map_err(|e| format!("解析时间失败: {}", e))?;
+
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+
+    let mut notes: Vec<(DateTime<chrono::FixedOffset>, NoteEntry)> = index.notes.into_iter()
+        .filter(|entry| include_archived || is_active(entry))
+        .filter_map(|entry| {
+            let created_time = DateTime::parse_from_rfc3339(&entry.created_at).ok()?;
+            (created_time >= from && created_time <= to).then_some((created_time, entry))
+        })
+        .collect();
+    notes.sort_by_key(|(created_time, _)| *created_time);
+    Ok(notes.into_iter().map(|(_, entry)| entry).collect())
+}
+
+// 恢复最近归档的一篇便签（archivedAt 最大且可解析的条目）并打开窗口，返回其ID
+// 没有归档便签时返回 None
+#[tauri::command]
+async fn restore_last_archived(window: tauri::WebviewWindow) -> Result<Option<String>, String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    let entry = index.notes.iter_mut()
+        .filter_map(|entry| {
+            let archived_time = DateTime::parse_from_rfc3339(entry.archived_at.as_deref()?).ok()?;
+            Some((archived_time, entry))
+        })
+        .max_by_key(|(archived_time, _)| *archived_time)
+        .map(|(_, entry)| entry);
+    let entry = match entry {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+
+    let prior = entry.clone();
+    internal_restore_note(entry, &Local::now());
+    let restored = entry.clone();
+    save_index(&notes_dir, &mut index)?;
+
+    record_undo_action(&app_handle, UndoKind::Restored { prior });
+    emit_index_changed(&app_handle, "restored", vec![restored.id.clone()]);
+    if let Err(e) = open_note_window(&app_handle, &restored).await {
+        error!(note_id = %restored.id, error = %e, "打开恢复的便签窗口失败");
+    }
+    Ok(Some(restored.id))
+}
+
+// 用归档便签的正文创建一篇全新的活跃便签（新ID、新的生命周期）并打开窗口，原归档便签保持不变，返回新ID
+// 加密便签的正文无法脱离原口令使用，直接报错
+#[tauri::command]
+async fn reactivate_as_new(window: tauri::WebviewWindow, id: String) -> Result<String, String> {
+    let settings = load_schedule_settings_from_disk();
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    let source = index.notes.iter().find(|entry| entry.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    if is_active(source) {
+        return Err("便签未归档".to_string());
+    }
+    let content = fs::read_to_string(notes_dir.join(&source.file.relative_path))
+        .map_err(|e| format!("读取便签文件失败: {}", e))?;
+    let body = extract_content_only(&content);
+    if is_encrypted(source) {
+        return Err("加密便签无法复用为新便签".to_string());
+    }
+
+    let new_entry = write_new_note_file(&app_handle, &notes_dir, &body, default_note_window_info(&notes_dir), &settings)?;
+    let new_id = new_entry.id.clone();
+    index.notes.push(new_entry.clone());
+    save_index(&notes_dir, &mut index)?;
+
+    emit_index_changed(&app_handle, "created", vec![new_id.clone()]);
+    if let Err(e) = apply_active_note_cap(&app_handle, &notes_dir, Some(&new_id), &settings) {
+        error!(error = %e, "自动归档超出上限的便签失败");
+    }
+    if let Err(e) = open_note_window(&app_handle, &new_entry).await {
+        error!(note_id = %new_id, error = %e, "打开新便签窗口失败");
+    }
+    Ok(new_id)
+}
+
+// 取出启动检查摘要（只返回一次），供前端加载后显示提示
+#[tauri::command]
+async fn take_startup_report(app_handle: tauri::AppHandle) -> Result<Option<StartupReport>, String> {
+    let app_state = app_handle.state::<AppState>();
+    let report = app_state.startup_report.lock().unwrap().take();
+    Ok(report)
+}
+
+// 撤销最近一次恢复/归档/删除操作，返回受影响的便签ID
+#[tauri::command]
+async fn undo_last_action(window: tauri::WebviewWindow) -> Result<String, String> {
+    let app_handle = window.app_handle().clone();
+    let action = {
+        let app_state = app_handle.state::<AppState>();
+        let mut last_action = app_state.last_action.lock().unwrap();
+        last_action.take()
+    };
+    let action = match action {
+        Some(action) if action.recorded_at.elapsed() <= UNDO_TIMEOUT => action,
+        _ => return Err("没有可撤销的操作".to_string()),
+    };
+
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    match action.kind {
+        UndoKind::Restored { prior } => {
+            let entry = index.notes.iter_mut().find(|note| note.id == prior.id)
+                .ok_or_else(|| "找不到指定的便签".to_string())?;
+            entry.archived_at = prior.archived_at;
+            entry.expire_at = prior.expire_at;
+            entry.last_active_at = prior.last_active_at;
+            save_index(&notes_dir, &mut index)?;
+
+            if let Some(note_window) = app_handle.get_webview_window(&format!("note-{}", prior.id)) {
+                let _ = note_window.hide();
+            }
+            emit_index_changed(&app_handle, "archived", vec![prior.id.clone()]);
+            Ok(prior.id)
+        }
+        UndoKind::Archived { prior } => {
+            let entry = index.notes.iter_mut().find(|note| note.id == prior.id)
+                .ok_or_else(|| "找不到指定的便签".to_string())?;
+            entry.archived_at = prior.archived_at;
+            entry.expire_at = prior.expire_at;
+            let restored = entry.clone();
+            save_index(&notes_dir, &mut index)?;
+
+            if let Err(e) = open_note_window(&app_handle, &restored).await {
+                error!(note_id = %restored.id, error = %e, "撤销归档后打开便签窗口失败");
+            }
+            emit_index_changed(&app_handle, "restored", vec![restored.id.clone()]);
+            Ok(restored.id)
+        }
+        UndoKind::Deleted { prior, file_bytes } => {
+            if index.notes.iter().any(|note| note.id == prior.id) {
+                return Err("该便签已在索引中".to_string());
+            }
+            if let Some(file_bytes) = file_bytes {
+                let file_path = resolve_relative_path(&notes_dir, &prior.file.relative_path)?;
+                write_file_safely(&file_path, file_bytes)
+                    .map_err(|e| format!("恢复便签文件失败: {}", e))?;
+            }
+            let id = prior.id.clone();
+            index.notes.push(prior);
+            save_index(&notes_dir, &mut index)?;
+            emit_index_changed(&app_handle, "created", vec![id.clone()]);
+            Ok(id)
+        }
+    }
+}
+
+// 返回便签的生命周期事件（按时间顺序）
+#[tauri::command]
+async fn get_note_timeline(window: tauri::WebviewWindow, id: String) -> Result<Vec<NoteEvent>, String> {
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+    index.notes.into_iter()
+        .find(|note| note.id == id)
+        .map(|note| note.events)
+        .ok_or_else(|| "找不到指定的便签".to_string())
+}
+
+// 修改便签的创建时间，可选地把文件移动到对应日期目录
+// 只改 createdAt，不触碰 expireAt / archivedAt 等生命周期字段
+#[tauri::command]
+async fn set_note_created_at(window: tauri::WebviewWindow, id: String, created_at: String, move_file: Option<bool>) -> Result<(), String> {
+    let settings = load_schedule_settings_from_disk();
+    let new_created_time = DateTime::parse_from_rfc3339(&created_at)
+        .map_err(|e| format!("解析时间失败: {}", e))?
+        .with_timezone(&Local);
+    let new_created_at = new_created_time.to_rfc3339();
+
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    let entry = index.notes.iter_mut().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+
+    let file_path = notes_dir.join(&entry.file.relative_path);
+    if !file_path.exists() {
+        return Err("便签文件不存在".to_string());
+    }
+
+    // 通过解析/构建函数重写 Front Matter 中的 createdAt
+    let existing_content = fs::read_to_string(&file_path)
+        .map_err(|e| format!("读取便签文件失败: {}", e))?;
+    let existing_id = parse_id_from_content(&existing_content)
+        .unwrap_or_else(|| entry.id.clone());
+    let body = extract_content_only(&existing_content);
+    let full_content = build_full_content(&existing_id, &new_created_at, &body, front_matter_style(&settings));
+
+    let target_path = if move_file.unwrap_or(false) {
+        let dated_dir = dated_note_dir(&notes_dir, new_created_time.date_naive(), &settings);
+        let file_name = file_path.file_name()
+            .ok_or_else(|| "无效的便签文件路径".to_string())?;
+        dated_dir.join(file_name)
+    } else {
+        file_path.clone()
+    };
+
+    if target_path != file_path && target_path.exists() {
+        return Err("目标日期目录中已存在同名文件".to_string());
+    }
+
+    write_file_safely(&target_path, full_content)
+        .map_err(|e| format!("写入便签文件失败: {}", e))?;
+    if target_path != file_path {
+        fs::remove_file(&file_path)
+            .map_err(|e| format!("删除原便签文件失败: {}", e))?;
+        entry.file.relative_path = target_path.strip_prefix(&notes_dir)
+            .unwrap_or(&target_path)
+            .to_string_lossy()
+            .to_string();
+    }
+
+    entry.created_at = new_created_at;
+
+    save_index(&notes_dir, &mut index)?;
+    emit_index_changed(&app_handle, "updated", vec![id]);
+    Ok(())
+}
+
+// 按当前的 folderGranularity 把已有便签文件移动到其创建日期对应的目录，返回被移动的便签ID
+// 目标目录中有同名文件时追加数字后缀；移动后留下的空目录会被删除
+#[tauri::command]
+async fn reorganize_notes(window: tauri::WebviewWindow) -> Result<Vec<String>, String> {
+    let settings = load_schedule_settings_from_disk();
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    let mut moved_ids = Vec::new();
+    for entry in index.notes.iter_mut() {
+        let file_path = match resolve_relative_path(&notes_dir, &entry.file.relative_path) {
+            Ok(file_path) if file_path.is_file() => file_path,
+            _ => continue,
+        };
+        let created_date = match DateTime::parse_from_rfc3339(&entry.created_at) {
+            Ok(created_time) => created_time.date_naive(),
+            Err(_) => continue,
+        };
+        let target_dir = dated_note_dir(&notes_dir, created_date, &settings);
+        if file_path.parent() == Some(target_dir.as_path()) {
+            continue;
+        }
+        let stem = file_path.file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| entry.id.clone());
+        fs::create_dir_all(&target_dir)
+            .map_err(|e| format!("创建日期目录失败: {}", e))?;
+        let target_path = unique_markdown_path(&target_dir, &stem, &file_path);
+        if let Err(e) = fs::rename(&file_path, &target_path) {
+            error!(note_id = %entry.id, error = %e, "移动便签文件失败");
+            continue;
+        }
+        if let Some(old_dir) = file_path.parent() {
+            if old_dir != notes_dir.join("notes") {
+                let _ = fs::remove_dir(old_dir);
+            }
         }
-        // 更新last_active_at和expire_at
-        let now = get_current_iso8601_time();
-        entry.last_active_at = now.clone();
-        
-        // 计算新的过期时间：当前时间 + 7天
-        let current_time = DateTime::parse_from_rfc3339(&now)
-            .map_err(|e| format!("解析当前时间失败: {}", e))?;
-        let new_expire_time = (current_time.naive_local()
-            .and_local_timezone(Local)
-            .unwrap() + Duration::days(7)).to_rfc3339();
-        entry.expire_at = Some(new_expire_time);
-
-        // 保存更新后的索引
-        index.app.name = "FadeNote".to_string(); // 确保app信息存在
-        // 不修改rebuildAt字段（V2规范：普通启动/更新禁止写入rebuildAt）
-        let json_content = serde_json::to_string_pretty(&index)
-            .map_err(|e| format!("序列化索引失败: {}", e))?;
-        write_file_safely(&index_path, json_content)
-            .map_err(|e| format!("写入索引文件失败: {}", e))?;
-
-        Ok(())
-    } else {
-        Err("找不到指定的便签".to_string())
+        entry.file.relative_path = target_path.strip_prefix(&notes_dir)
+            .unwrap_or(&target_path)
+            .to_string_lossy()
+            .to_string();
+        moved_ids.push(entry.id.clone());
     }
-}
 
-// 恢复便签 - 统一入口
-fn internal_restore_note(entry: &mut NoteEntry, now: &DateTime<Local>) {
-    entry.archived_at = None;
-    entry.last_active_at = now.to_rfc3339();
-    let new_expire_time = now.with_timezone(&chrono::Utc) + Duration::days(7);
-    entry.expire_at = Some(new_expire_time.to_rfc3339());
+    if !moved_ids.is_empty() {
+        save_index(&notes_dir, &mut index)?;
+        emit_index_changed(&app_handle, "updated", moved_ids.clone());
+    }
+    Ok(moved_ids)
 }
 
-// 设置便签固定状态
+// 按 slug 重命名便签文件（同一日期目录内），ID 与 Front Matter 保持不变
+// slug 为空时恢复为 `{uuid}.md`，返回新的相对路径
 #[tauri::command]
-async fn set_note_pinned(window: tauri::WebviewWindow, id: String, pinned: bool) -> Result<(), String> {
+async fn set_note_slug(window: tauri::WebviewWindow, id: String, slug: Option<String>) -> Result<String, String> {
+    let app_handle = window.app_handle().clone();
     let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
-    
-    // 从索引中获取文件路径
-    let index_path = notes_dir.join("index.json");
-    if !index_path.exists() {
-        return Err("索引文件不存在".to_string());
+    let mut index = read_index(&notes_dir)?;
+
+    let entry = index.notes.iter_mut().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+
+    let file_path = resolve_relative_path(&notes_dir, &entry.file.relative_path)?;
+    if !file_path.exists() {
+        return Err("便签文件不存在".to_string());
     }
 
-    let mut index: IndexFile = {
-        let content = fs::read_to_string(&index_path)
-            .map_err(|e| format!("读取索引文件失败: {}", e))?;
-        serde_json::from_str(&content)
-            .map_err(|e| format!("解析索引文件失败: {}", e))?
+    let stem = match slug.as_deref().filter(|slug| !slug.trim().is_empty()) {
+        Some(slug) => sanitize_slug(slug).ok_or_else(|| "无效的文件名".to_string())?,
+        None => entry.id.clone(),
     };
+    let parent_dir = file_path.parent()
+        .ok_or_else(|| "无效的便签文件路径".to_string())?;
+    let target_path = unique_markdown_path(parent_dir, &stem, &file_path);
+
+    if target_path != file_path {
+        fs::rename(&file_path, &target_path)
+            .map_err(|e| format!("重命名便签文件失败: {}", e))?;
+        entry.file.relative_path = target_path.strip_prefix(&notes_dir)
+            .unwrap_or(&target_path)
+            .to_string_lossy()
+            .to_string();
+    }
 
-    // 查找并更新指定ID的便签
-    if let Some(entry) = index.notes.iter_mut().find(|note| note.id == id) {
-        entry.pinned = pinned;
-        if !pinned && entry.archived_at.is_none() {
-            let now = Local::now();
-            entry.last_active_at = now.to_rfc3339();
-            entry.expire_at = Some((now + Duration::days(7)).to_rfc3339());
+    let relative_path = entry.file.relative_path.clone();
+    save_index(&notes_dir, &mut index)?;
+    emit_index_changed(&app_handle, "updated", vec![id.clone()]);
+    Ok(relative_path)
+}
+
+// archiveOnClose：等前端保存完（最长 SAVE_ALL_TIMEOUT）后归档便签并销毁窗口；固定便签只保持隐藏
+// 前端关闭流程会触发两次关闭请求，同一便签只处理一次
+fn archive_note_on_close(app_handle: tauri::AppHandle, id: String) {
+    {
+        let app_state = app_handle.state::<AppState>();
+        if !app_state.closing_notes.lock().unwrap().insert(id.clone()) {
+            return;
         }
-        
-        // 保存更新后的索引
-        index.app.name = "FadeNote".to_string(); // 确保app信息存在
-        // 不修改rebuildAt字段（V2规范：普通启动/更新禁止写入rebuildAt）
-        let json_content = serde_json::to_string_pretty(&index)
-            .map_err(|e| format!("序列化索引失败: {}", e))?;
-        write_file_safely(&index_path, json_content)
-            .map_err(|e| format!("写入索引文件失败: {}", e))?;
+    }
+    let (call_id, flushed) = register_flush_waiter(&app_handle, std::slice::from_ref(&id));
+
+    std::thread::spawn(move || {
+        let _ = flushed.recv_timeout(SAVE_ALL_TIMEOUT);
+        unregister_flush_waiter(&app_handle, call_id);
+
+        let archive_result = get_app_data_dir().and_then(|app_data_dir| {
+            let mut index = read_index(&app_data_dir)?;
+            let entry = match index.notes.iter_mut().find(|entry| entry.id == id && is_active(entry) && !entry.pinned) {
+                Some(entry) => entry,
+                None => return Ok(false),
+            };
+            archive_note(entry, &Local::now())?;
+            save_index(&app_data_dir, &mut index)?;
+            Ok(true)
+        });
+        match archive_result {
+            Ok(true) => {
+                info!(note_id = %id, "关闭时归档便签");
+                emit_index_changed(&app_handle, "archived", vec![id.clone()]);
+                if let Some(note_window) = app_handle.get_webview_window(&format!("note-{}", id)) {
+                    let _ = note_window.destroy();
+                }
+            }
+            Ok(false) => {}
+            Err(e) => error!(note_id = %id, error = %e, "关闭时归档便签失败"),
+        }
+        app_handle.state::<AppState>().closing_notes.lock().unwrap().remove(&id);
+    });
+}
 
-        Ok(())
-    } else {
-        Err("找不到指定的便签".to_string())
+// 为一次等待登记要回写的便签，返回本次调用的ID和接收已保存便签ID的通道；各调用互不影响
+fn register_flush_waiter(app_handle: &tauri::AppHandle, note_ids: &[String]) -> (Uuid, std::sync::mpsc::Receiver<String>) {
+    let call_id = Uuid::new_v4();
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let app_state = app_handle.state::<AppState>();
+    let mut flush_waiters = app_state.flush_waiters.lock().unwrap();
+    for id in note_ids {
+        flush_waiters.entry(id.clone()).or_default().push((call_id, sender.clone()));
     }
+    (call_id, receiver)
 }
 
-// 删除便签
-#[tauri::command]
-async fn delete_note(window: tauri::WebviewWindow, id: String) -> Result<(), String> {
-    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
-    
-    // 从索引中获取文件路径
-    let index_path = notes_dir.join("index.json");
-    if !index_path.exists() {
-        return Err("索引文件不存在".to_string());
+// 结束等待后清除本次调用尚未收到回写的登记
+fn unregister_flush_waiter(app_handle: &tauri::AppHandle, call_id: Uuid) {
+    let app_state = app_handle.state::<AppState>();
+    let mut flush_waiters = app_state.flush_waiters.lock().unwrap();
+    for waiters in flush_waiters.values_mut() {
+        waiters.retain(|(waiter_id, _)| *waiter_id != call_id);
     }
+    flush_waiters.retain(|_, waiters| !waiters.is_empty());
+}
 
-    let mut index: IndexFile = {
-        let content = fs::read_to_string(&index_path)
-            .map_err(|e| format!("读取索引文件失败: {}", e))?;
-        serde_json::from_str(&content)
-            .map_err(|e| format!("解析索引文件失败: {}", e))?
-    };
+// 保存成功后通知所有在等待该便签回写的调用
+fn mark_note_flushed(app_handle: &tauri::AppHandle, id: &str) {
+    let waiters = app_handle.state::<AppState>().flush_waiters.lock().unwrap().remove(id);
+    for (_, sender) in waiters.unwrap_or_default() {
+        let _ = sender.send(id.to_string());
+    }
+}
 
-    // 查找并删除指定ID的便签
-    if let Some(pos) = index.notes.iter().position(|note| note.id == id) {
-        let entry = &index.notes[pos];
-        
-        // 构造文件路径并删除文件
-        let file_path = notes_dir.join(&entry.file.relative_path);
-        if file_path.exists() {
-            fs::remove_file(&file_path)
-                .map_err(|e| format!("删除便签文件失败: {}", e))?;
+// 收到保存请求后前端确实会回写的便签：活跃、未超出大小上限（超出时只读），受保护的便签需已解锁
+fn will_flush_on_request(app_handle: &tauri::AppHandle, notes_dir: &Path, entry: &NoteEntry, max_note_bytes: u64) -> bool {
+    if !is_active(entry) {
+        return false;
+    }
+    if entry.protected.is_some() {
+        let app_state = app_handle.state::<AppState>();
+        let unlocked = app_state.unlocked_notes.lock().unwrap()
+            .get(&entry.id)
+            .is_some_and(|session| session.unlocked_at.elapsed() <= UNLOCK_TIMEOUT);
+        if !unlocked {
+            return false;
         }
-        
-        // 从索引中移除该便签
-        index.notes.remove(pos);
-        
-        // 保存更新后的索引
-        index.app.name = "FadeNote".to_string(); // 确保app信息存在
-        // 不修改rebuildAt字段（V2规范：普通启动/更新禁止写入rebuildAt）
-        let json_content = serde_json::to_string_pretty(&index)
-            .map_err(|e| format!("序列化索引失败: {}", e))?;
-        write_file_safely(&index_path, json_content)
-            .map_err(|e| format!("写入索引文件失败: {}", e))?;
-
-        Ok(())
-    } else {
-        Err("找不到指定的便签".to_string())
     }
+    fs::metadata(notes_dir.join(&entry.file.relative_path))
+        .is_ok_and(|metadata| metadata.len() <= max_note_bytes)
 }
 
-// 恢复归档的便签
+// 通知所有打开的便签窗口立即保存当前内容（不刷新活跃时间），等待回写完成后返回成功保存的数量
 #[tauri::command]
-async fn restore_note(window: tauri::WebviewWindow, id: String) -> Result<(), String> {
+async fn save_all_now(window: tauri::WebviewWindow) -> Result<usize, String> {
+    let app_handle = window.app_handle().clone();
     let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
-    
-    // 从索引中获取文件路径
-    let index_path = notes_dir.join("index.json");
-    if !index_path.exists() {
-        return Err("索引文件不存在".to_string());
+    let index = read_index(&notes_dir)?;
+    let max_note_bytes = load_schedule_settings_from_disk().max_note_bytes;
+    let open_ids: std::collections::HashSet<String> = app_handle.webview_windows()
+        .into_keys()
+        .filter(|label| !is_peek_label(label))
+        .filter_map(|label| label.strip_prefix("note-").map(|id| id.to_string()))
+        .collect();
+    // 只等待会真正回写的窗口：只读（超大）和锁定的便签前端会跳过保存
+    let note_ids: Vec<String> = index.notes.iter()
+        .filter(|entry| open_ids.contains(&entry.id))
+        .filter(|entry| will_flush_on_request(&app_handle, &notes_dir, entry, max_note_bytes))
+        .map(|entry| entry.id.clone())
+        .collect();
+    if note_ids.is_empty() {
+        return Ok(0);
     }
 
-    let mut index: IndexFile = {
-        let content = fs::read_to_string(&index_path)
-            .map_err(|e| format!("读取索引文件失败: {}", e))?;
-        serde_json::from_str(&content)
-            .map_err(|e| format!("解析索引文件失败: {}", e))?
-    };
+    let (call_id, flushed) = register_flush_waiter(&app_handle, &note_ids);
+    for id in &note_ids {
+        let label = format!("note-{}", id);
+        let _ = app_handle.emit_to(label.as_str(), "fadenote://save-now", id.clone());
+    }
 
-    // 查找并恢复指定ID的便签
-    if let Some(entry) = index.notes.iter_mut().find(|note| note.id == id) {
-        if entry.archived_at.is_some() {
-            let now = Local::now();
-            internal_restore_note(entry, &now);
+    // 在阻塞线程上等待回写通知，最多 SAVE_ALL_TIMEOUT，超时未回写的窗口不计入
+    let expected = note_ids.len();
+    let saved = tauri::async_runtime::spawn_blocking(move || {
+        let deadline = std::time::Instant::now() + SAVE_ALL_TIMEOUT;
+        let mut saved = std::collections::HashSet::new();
+        while saved.len() < expected {
+            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+            match flushed.recv_timeout(remaining) {
+                Ok(id) => {
+                    saved.insert(id);
+                }
+                Err(_) => break,
+            }
         }
+        saved.len()
+    })
+    .await
+    .map_err(|e| format!("等待便签保存失败: {}", e))?;
 
-        // 保存更新后的索引
-        index.app.name = "FadeNote".to_string(); // 确保app信息存在
-        // 不修改rebuildAt字段（V2规范：普通启动/更新禁止写入rebuildAt）
-        let json_content = serde_json::to_string_pretty(&index)
-            .map_err(|e| format!("序列化索引失败: {}", e))?;
-        write_file_safely(&index_path, json_content)
-            .map_err(|e| format!("写入索引文件失败: {}", e))?;
-
-        Ok(())
-    } else {
-        Err("找不到指定的便签".to_string())
-    }
+    unregister_flush_waiter(&app_handle, call_id);
+    Ok(saved)
 }
 
 // 保存便签内容
 #[tauri::command]
-async fn save_note_content(window: tauri::WebviewWindow, id: String, content: String) -> Result<(), String> {
+async fn save_note_content(window: tauri::WebviewWindow, id: String, content: String, unlock_token: Option<String>) -> Result<(), String> {
+    let settings = load_schedule_settings_from_disk();
+    check_note_size(&content, &settings)?;
+    let app_handle = window.app_handle().clone();
     let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
-    
-    
-    
-    // 从索引中获取文件路径
-    let index_path = notes_dir.join("index.json");
-    if !index_path.exists() {
-        return Err("索引文件不存在".to_string());
-    }
-
-    let mut index: IndexFile = {
-        let content_str = fs::read_to_string(&index_path)
-            .map_err(|e| format!("读取索引文件失败: {}", e))?;
-        serde_json::from_str(&content_str)
-            .map_err(|e| format!("解析索引文件失败: {}", e))?
-    };
+    let mut index = read_index(&notes_dir)?;
 
     // 查找并更新活动时间
     if let Some(update_entry) = index.notes.iter_mut().find(|note| note.id == id) {
@@ -1175,132 +4591,462 @@ async fn save_note_content(window: tauri::WebviewWindow, id: String, content: St
         let created_at = extract_created_at_from_content(&existing_content)
             .unwrap_or_else(|| get_current_iso8601_time());
 
-        // 构建新内容
-        let full_content = build_full_content(&existing_id, &created_at, &content);
-
-        // 写入文件
-        write_file_safely(&file_path, full_content)
-            .map_err(|e| format!("写入便签文件失败: {}", e))?;
+        // 构建新内容（加密便签重新加密正文）
+        let (body, preview, encryption) = body_for_write(&app_handle, update_entry, &existing_content, &content, unlock_token.as_deref(), &settings)?;
+        let full_content = build_full_content(&existing_id, &created_at, &body, front_matter_style(&settings));
+
+        // 写入文件（内容未变化时跳过写入，索引仍照常更新）
+        // 加密正文每次的 nonce 都不同，比较的是解密后的正文而不是密文
+        let session = unlocked_session(&app_handle, &id, unlock_token.as_deref());
+        let existing_plain = decrypt_entry_body(&app_handle, update_entry, extract_content_only(&existing_content), session.as_ref()).ok();
+        let unchanged = encryption == update_entry.encryption
+            && note_file_unchanged(&existing_content, existing_plain.as_deref(), &content, &existing_id, &created_at, front_matter_style(&settings));
+        if !unchanged {
+            write_file_safely(&file_path, full_content)
+                .map_err(|e| format!("写入便签文件失败: {}", e))?;
+        }
 
         // 更新活动时间
         let now = get_current_iso8601_time();
         update_entry.last_active_at = now.clone();
+        record_note_event(update_entry, "edited", &now);
         
-        // 计算新的过期时间：当前时间 + 7天
-        let current_time = DateTime::parse_from_rfc3339(&now)
-            .map_err(|e| format!("解析当前时间失败: {}", e))?;
-        let new_expire_time = (current_time.naive_local()
-            .and_local_timezone(Local)
-            .unwrap() + Duration::days(7)).to_rfc3339();
-        update_entry.expire_at = Some(new_expire_time);
+        // 计算新的过期时间：按 fadeAnchor 从当前时间或创建时间起算 7 天
+        update_entry.expire_at = Some(renewed_expire_at(update_entry, &Local::now()));
         
         // 更新cachedPreview：从内容中提取第一行作为预览
-        update_entry.cached_preview = extract_first_line_preview(&content);
+        update_entry.cached_preview = preview;
+        update_entry.encryption = encryption;
         
         // 保存更新后的索引
-        let json_content = serde_json::to_string_pretty(&index)
-            .map_err(|e| format!("序列化索引失败: {}", e))?;
-        write_file_safely(&index_path, json_content)
-            .map_err(|e| format!("写入索引文件失败: {}", e))?;
+        save_index(&notes_dir, &mut index)?;
+        emit_index_changed(&app_handle, "updated", vec![id.clone()]);
 
+        mark_note_flushed(&app_handle, &id);
         Ok(())
     } else {
         Err("找不到指定的便签".to_string())
     }
 }
 
-// 提取内容预览：从内容中提取第一行作为预览
+// 提取内容预览：从内容中提取第一行作为预览
+#[tauri::command]
+async fn save_note_content_without_touch(window: tauri::WebviewWindow, id: String, content: String, unlock_token: Option<String>) -> Result<(), String> {
+    let settings = load_schedule_settings_from_disk();
+    check_note_size(&content, &settings)?;
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let mut index = read_index(&notes_dir)?;
+
+    if let Some(update_entry) = index.notes.iter_mut().find(|note| note.id == id) {
+        if !is_active(update_entry) {
+            return Ok(());
+        }
+
+        let file_path = notes_dir.join(&update_entry.file.relative_path);
+        if !file_path.exists() {
+            return Err("note file not found".to_string());
+        }
+
+        let existing_content = fs::read_to_string(&file_path).unwrap_or_default();
+        let existing_id = parse_id_from_content(&existing_content)
+            .unwrap_or_else(|| update_entry.id.clone());
+        let created_at = extract_created_at_from_content(&existing_content)
+            .unwrap_or_else(|| update_entry.created_at.clone());
+        let (body, preview, encryption) = body_for_write(&app_handle, update_entry, &existing_content, &content, unlock_token.as_deref(), &settings)?;
+        let full_content = build_full_content(&existing_id, &created_at, &body, front_matter_style(&settings));
+
+        write_file_safely(&file_path, full_content)
+            .map_err(|e| format!("write note failed: {}", e))?;
+        update_entry.cached_preview = preview;
+        update_entry.encryption = encryption;
+
+        save_index(&notes_dir, &mut index)?;
+        emit_index_changed(&app_handle, "updated", vec![id.clone()]);
+
+        mark_note_flushed(&app_handle, &id);
+        Ok(())
+    } else {
+        Err("note not found".to_string())
+    }
+}
+
+// 更新窗口位置和大小
+#[tauri::command]
+async fn update_note_window(window: tauri::WebviewWindow, id: String, x: f64, y: f64, width: f64, height: f64) -> Result<(), String> {
+    // 放大状态下只持久化基础尺寸，重启后恢复正常大小
+    let base_size = {
+        let app_state = window.state::<AppState>();
+        let zoomed_notes = app_state.zoomed_notes.lock().unwrap();
+        zoomed_notes.get(&id).copied()
+    };
+    let (width, height) = base_size.unwrap_or((width, height));
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    
+    // 从索引中更新窗口信息
+    let mut index = read_index(&notes_dir)?;
+
+    if let Some(entry) = index.notes.iter_mut().find(|note| note.id == id) {
+        if let Some(ref mut window_info) = entry.window {
+            let (x, y) = to_stored_position(&app_handle, window_info.monitor_index, x, y);
+            window_info.x = x;
+            window_info.y = y;
+            window_info.width = width;
+            window_info.height = height;
+        } else {
+            // 如果窗口信息不存在，创建一个新的
+            entry.window = Some(WindowInfo {
+                x,
+                y,
+                width,
+                height,
+                decorated: false,
+                on_all_desktops: false,
+                monitor_index: None,
+            });
+        }
+        
+        // 保存更新后的索引；只改窗口位置，不通知 index-changed
+        save_index(&notes_dir, &mut index)?;
+
+        Ok(())
+    } else {
+        Err("找不到指定的便签".to_string())
+    }
+}
+
+// 在基础尺寸和放大尺寸之间切换便签窗口，返回切换后是否处于放大状态
+#[tauri::command]
+async fn toggle_note_zoom(window: tauri::WebviewWindow, id: String, zoom_width: f64, zoom_height: f64) -> Result<bool, String> {
+    let app_handle = window.app_handle().clone();
+    let note_window = app_handle.get_webview_window(&format!("note-{}", id))
+        .ok_or_else(|| "便签窗口不存在".to_string())?;
+
+    let previous_size = {
+        let app_state = app_handle.state::<AppState>();
+        let mut zoomed_notes = app_state.zoomed_notes.lock().unwrap();
+        zoomed_notes.remove(&id)
+    };
+
+    if let Some((width, height)) = previous_size {
+        note_window.set_size(tauri::LogicalSize::new(width, height))
+            .map_err(|e| e.to_string())?;
+        return Ok(false);
+    }
+
+    // 以索引中保存的尺寸作为基础尺寸
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let index = read_index(&notes_dir)?;
+    let entry = index.notes.iter().find(|note| note.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    let base_size = entry.window.as_ref()
+        .map(|window_info| (window_info.width, window_info.height))
+        .unwrap_or((DEFAULT_NOTE_WIDTH, DEFAULT_NOTE_HEIGHT));
+
+    {
+        let app_state = app_handle.state::<AppState>();
+        let mut zoomed_notes = app_state.zoomed_notes.lock().unwrap();
+        zoomed_notes.insert(id.clone(), base_size);
+    }
+    note_window.set_size(tauri::LogicalSize::new(zoom_width.max(NOTE_MIN_WIDTH), zoom_height.max(NOTE_MIN_HEIGHT)))
+        .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+// 把所有可见的便签窗口从主显示器左上角开始按网格排列，并保存新位置，返回排列的便签数
+#[tauri::command]
+async fn arrange_notes_grid(window: tauri::WebviewWindow, columns: u32) -> Result<usize, String> {
+    let app_handle = window.app_handle().clone();
+    let monitor = window.primary_monitor()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "找不到主显示器".to_string())?;
+    let scale_factor = monitor.scale_factor();
+    let origin = monitor.position().to_logical::<f64>(scale_factor);
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let note_windows = visible_note_windows(&app_handle);
+
+    let columns = columns.max(1) as usize;
+    let mut y = origin.y + GRID_GAP;
+    for row in note_windows.chunks(columns) {
+        let mut x = origin.x + GRID_GAP;
+        let mut row_height: f64 = 0.0;
+        for (label, note_window) in row {
+            let size = note_window.inner_size()
+                .map_err(|e| e.to_string())?
+                .to_logical::<f64>(note_window.scale_factor().unwrap_or(scale_factor));
+            note_window.set_position(tauri::LogicalPosition::new(x, y))
+                .map_err(|e| e.to_string())?;
+
+            // 放大状态下只持久化基础尺寸
+            let id = label.trim_start_matches("note-");
+            let base_size = app_handle.state::<AppState>().zoomed_notes.lock().unwrap().get(id).copied();
+            let (width, height) = base_size.unwrap_or((size.width, size.height));
+            update_note_window_info(&app_handle, &notes_dir, id, x, y, width, height).await?;
+
+            x += size.width + GRID_GAP;
+            row_height = row_height.max(size.height);
+        }
+        y += row_height + GRID_GAP;
+    }
+    Ok(note_windows.len())
+}
+
+// 当前可见且未最小化的便签窗口（不含只读预览窗口），按标签排序
+fn visible_note_windows(app_handle: &tauri::AppHandle) -> Vec<(String, tauri::WebviewWindow)> {
+    let mut note_windows: Vec<(String, tauri::WebviewWindow)> = app_handle.webview_windows()
+        .into_iter()
+        .filter(|(label, note_window)| {
+            label.starts_with("note-")
+                && !is_peek_label(label)
+                && note_window.is_visible().unwrap_or(false)
+                && !note_window.is_minimized().unwrap_or(false)
+        })
+        .collect();
+    note_windows.sort_by(|(a, _), (b, _)| a.cmp(b));
+    note_windows
+}
+
+// 在主显示器工作区内重新排列可见的便签窗口并保存位置，返回排列的窗口数
+// cascade：从工作区左上角起每个窗口偏移 30px，超出工作区时回到起点；
+// tile：按接近正方形的网格平铺，窗口缩放到格子大小（不小于最小尺寸）
+#[tauri::command]
+async fn arrange_windows(window: tauri::WebviewWindow, mode: String) -> Result<usize, String> {
+    let app_handle = window.app_handle().clone();
+    let monitor = window.primary_monitor()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "找不到主显示器".to_string())?;
+    let (area_x, area_y, area_width, area_height) = monitor_work_area(&monitor);
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let note_windows = visible_note_windows(&app_handle);
+    if note_windows.is_empty() {
+        return Ok(0);
+    }
+
+    match mode.as_str() {
+        "cascade" => {
+            let (mut x, mut y) = (area_x, area_y);
+            for (label, note_window) in &note_windows {
+                let size = note_window.inner_size()
+                    .map_err(|e| e.to_string())?
+                    .to_logical::<f64>(note_window.scale_factor().unwrap_or(monitor.scale_factor()));
+                if x + size.width > area_x + area_width || y + size.height > area_y + area_height {
+                    (x, y) = (area_x, area_y);
+                }
+                note_window.set_position(tauri::LogicalPosition::new(x, y))
+                    .map_err(|e| e.to_string())?;
+
+                // 放大状态下只持久化基础尺寸
+                let id = label.trim_start_matches("note-");
+                let base_size = app_handle.state::<AppState>().zoomed_notes.lock().unwrap().get(id).copied();
+                let (width, height) = base_size.unwrap_or((size.width, size.height));
+                update_note_window_info(&app_handle, &notes_dir, id, x, y, width, height).await?;
+
+                x += ARRANGE_CASCADE_OFFSET;
+                y += ARRANGE_CASCADE_OFFSET;
+            }
+        }
+        "tile" => {
+            let columns = (note_windows.len() as f64).sqrt().ceil() as usize;
+            let rows = note_windows.len().div_ceil(columns);
+            let cell_width = ((area_width - GRID_GAP) / columns as f64 - GRID_GAP).max(NOTE_MIN_WIDTH);
+            let cell_height = ((area_height - GRID_GAP) / rows as f64 - GRID_GAP).max(NOTE_MIN_HEIGHT);
+            for (position, (label, note_window)) in note_windows.iter().enumerate() {
+                let x = area_x + GRID_GAP + (position % columns) as f64 * (cell_width + GRID_GAP);
+                let y = area_y + GRID_GAP + (position / columns) as f64 * (cell_height + GRID_GAP);
+                note_window.set_size(tauri::LogicalSize::new(cell_width, cell_height))
+                    .map_err(|e| e.to_string())?;
+                note_window.set_position(tauri::LogicalPosition::new(x, y))
+                    .map_err(|e| e.to_string())?;
+
+                // 平铺后的尺寸即为新的基础尺寸，退出放大状态
+                let id = label.trim_start_matches("note-");
+                app_handle.state::<AppState>().zoomed_notes.lock().unwrap().remove(id);
+                update_note_window_info(&app_handle, &notes_dir, id, x, y, cell_width, cell_height).await?;
+            }
+        }
+        _ => return Err(format!("不支持的排列方式: {}", mode)),
+    }
+    Ok(note_windows.len())
+}
+
+// 布局文件路径 layouts/{name}.json；名称不能为空，也不能包含路径分隔符
+fn layout_path(notes_dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let name = name.trim();
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\', ':']) {
+        return Err(format!("无效的布局名称: {}", name));
+    }
+    Ok(notes_dir.join("layouts").join(format!("{}.json", name)))
+}
+
+// 把所有活跃便签当前保存的窗口信息存为命名布局，返回保存的窗口数
+#[tauri::command]
+async fn save_layout(window: tauri::WebviewWindow, name: String) -> Result<usize, String> {
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let path = layout_path(&notes_dir, &name)?;
+    let index = read_index(&notes_dir)?;
+
+    let layout = NoteLayout {
+        saved_at: get_current_iso8601_time(),
+        windows: index.notes.into_iter()
+            .filter(is_active)
+            .filter_map(|entry| Some(LayoutWindow { window: entry.window?, id: entry.id }))
+            .collect(),
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建布局目录失败: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(&layout)
+        .map_err(|e| format!("序列化布局失败: {}", e))?;
+    write_file_safely(&path, content).map_err(|e| format!("写入布局文件失败: {}", e))?;
+    Ok(layout.windows.len())
+}
+
+// 按命名布局移动（或创建）便签窗口并写回索引，返回应用了布局的便签ID
+// 布局中已不存在或已归档的便签跳过，不在布局中的便签保持原位；系统标题栏等窗口选项沿用当前设置
+#[tauri::command]
+async fn apply_layout(window: tauri::WebviewWindow, name: String) -> Result<Vec<String>, String> {
+    let app_handle = window.app_handle().clone();
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let path = layout_path(&notes_dir, &name)?;
+    let layout: NoteLayout = {
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("读取布局文件失败: {}", e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("解析布局文件失败: {}", e))?
+    };
+    let mut index = read_index(&notes_dir)?;
+
+    let mut applied = Vec::new();
+    for layout_window in layout.windows {
+        let entry = match index.notes.iter_mut().find(|entry| entry.id == layout_window.id && is_active(entry)) {
+            Some(entry) => entry,
+            None => continue,
+        };
+        let mut window_info = layout_window.window;
+        if let Some(current) = entry.window.as_ref() {
+            window_info.decorated = current.decorated;
+            window_info.on_all_desktops = current.on_all_desktops;
+        }
+        entry.window = Some(window_info);
+        applied.push(entry.clone());
+    }
+    if applied.is_empty() {
+        return Ok(Vec::new());
+    }
+    save_index(&notes_dir, &mut index)?;
+
+    for entry in &applied {
+        let window_info = match entry.window.as_ref() {
+            Some(window_info) => window_info,
+            None => continue,
+        };
+        match app_handle.get_webview_window(&format!("note-{}", entry.id)) {
+            Some(note_window) => {
+                let (x, y) = to_global_position(&app_handle, window_info.monitor_index, window_info.x, window_info.y);
+                let _ = note_window.set_position(tauri::LogicalPosition::new(x, y));
+                let _ = note_window.set_size(tauri::LogicalSize::new(window_info.width, window_info.height));
+                let _ = note_window.show();
+            }
+            None => {
+                if let Err(e) = open_note_window(&app_handle, entry).await {
+                    error!(note_id = %entry.id, error = %e, "按布局创建便签窗口失败");
+                }
+            }
+        }
+    }
+
+    let applied_ids: Vec<String> = applied.into_iter().map(|entry| entry.id).collect();
+    emit_index_changed(&app_handle, "updated", applied_ids.clone());
+    Ok(applied_ids)
+}
+
+// 以只读窗口临时查看归档便签（标签 note-{id}-peek），不恢复便签、不修改 archivedAt / expireAt
+// 预览窗口已存在时直接显示并聚焦
 #[tauri::command]
-async fn save_note_content_without_touch(window: tauri::WebviewWindow, id: String, content: String) -> Result<(), String> {
+async fn peek_archived_window(window: tauri::WebviewWindow, id: String) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
     let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
-    let index_path = notes_dir.join("index.json");
-    if !index_path.exists() {
-        return Err("index not found".to_string());
+    let index = read_index(&notes_dir)?;
+    let entry = index.notes.iter().find(|entry| entry.id == id)
+        .ok_or_else(|| "找不到指定的便签".to_string())?;
+    if is_active(entry) {
+        return Err("便签未归档".to_string());
     }
 
-    let mut index: IndexFile = {
-        let content_str = fs::read_to_string(&index_path)
-            .map_err(|e| format!("read index failed: {}", e))?;
-        serde_json::from_str(&content_str)
-            .map_err(|e| format!("parse index failed: {}", e))?
-    };
-
-    if let Some(update_entry) = index.notes.iter_mut().find(|note| note.id == id) {
-        if !is_active(update_entry) {
-            return Ok(());
-        }
-
-        let file_path = notes_dir.join(&update_entry.file.relative_path);
-        if !file_path.exists() {
-            return Err("note file not found".to_string());
-        }
+    let label = format!("note-{}{}", id, PEEK_LABEL_SUFFIX);
+    if let Some(peek_window) = app_handle.get_webview_window(&label) {
+        let _ = peek_window.show();
+        let _ = peek_window.set_focus();
+        return Ok(());
+    }
 
-        let existing_content = fs::read_to_string(&file_path).unwrap_or_default();
-        let existing_id = parse_id_from_content(&existing_content)
-            .unwrap_or_else(|| update_entry.id.clone());
-        let created_at = extract_created_at_from_content(&existing_content)
-            .unwrap_or_else(|| update_entry.created_at.clone());
-        let full_content = build_full_content(&existing_id, &created_at, &content);
+    let (width, height) = entry.window.as_ref()
+        .map_or((DEFAULT_NOTE_WIDTH, DEFAULT_NOTE_HEIGHT), |window_info| (window_info.width, window_info.height));
+    tauri::WebviewWindowBuilder::new(
+        &app_handle,
+        &label,
+        tauri::WebviewUrl::App(format!("index.html?noteId={}&peek=1", id).into()),
+    )
+    .title(window_title_from_preview(entry.cached_preview.as_ref()))
+    .inner_size(width.max(NOTE_MIN_WIDTH), height.max(NOTE_MIN_HEIGHT))
+    .min_inner_size(NOTE_MIN_WIDTH, NOTE_MIN_HEIGHT)
+    .resizable(true)
+    .decorations(false)
+    .maximizable(false)
+    .transparent(false)
+    .center()
+    .build()
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
 
-        write_file_safely(&file_path, full_content)
-            .map_err(|e| format!("write note failed: {}", e))?;
-        update_entry.cached_preview = extract_first_line_preview(&content);
+// 专注模式：隐藏除指定便签外的所有便签窗口，不修改索引
+#[tauri::command]
+async fn focus_mode(window: tauri::WebviewWindow, id: String) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let focus_label = format!("note-{}", id);
+    let focus_window = app_handle.get_webview_window(&focus_label)
+        .ok_or_else(|| "便签窗口不存在".to_string())?;
 
-        let json_content = serde_json::to_string_pretty(&index)
-            .map_err(|e| format!("serialize index failed: {}", e))?;
-        write_file_safely(&index_path, json_content)
-            .map_err(|e| format!("write index failed: {}", e))?;
+    let app_state = app_handle.state::<AppState>();
+    let mut hidden_labels = app_state.focus_hidden_labels.lock().unwrap();
+    // 切换专注对象时，新的专注便签不再属于被隐藏的窗口
+    hidden_labels.retain(|label| label != &focus_label);
 
-        Ok(())
-    } else {
-        Err("note not found".to_string())
+    for (label, note_window) in app_handle.webview_windows() {
+        if !label.starts_with("note-") || label == focus_label {
+            continue;
+        }
+        if note_window.is_visible().unwrap_or(false) {
+            let _ = note_window.hide();
+            if !hidden_labels.contains(&label) {
+                hidden_labels.push(label);
+            }
+        }
     }
+
+    let _ = focus_window.show();
+    let _ = focus_window.set_focus();
+    Ok(())
 }
 
-// 更新窗口位置和大小
+// 退出专注模式：恢复进入专注模式时可见的便签窗口
 #[tauri::command]
-async fn update_note_window(window: tauri::WebviewWindow, id: String, x: f64, y: f64, width: f64, height: f64) -> Result<(), String> {
-    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
-    
-    // 从索引中更新窗口信息
-    let index_path = notes_dir.join("index.json");
-    if !index_path.exists() {
-        return Err("索引文件不存在".to_string());
-    }
-
-    let mut index: IndexFile = {
-        let content = fs::read_to_string(&index_path)
-            .map_err(|e| format!("读取索引文件失败: {}", e))?;
-        serde_json::from_str(&content)
-            .map_err(|e| format!("解析索引文件失败: {}", e))?
+async fn exit_focus_mode(window: tauri::WebviewWindow) -> Result<(), String> {
+    let app_handle = window.app_handle().clone();
+    let hidden_labels = {
+        let app_state = app_handle.state::<AppState>();
+        let mut hidden_labels = app_state.focus_hidden_labels.lock().unwrap();
+        std::mem::take(&mut *hidden_labels)
     };
 
-    if let Some(entry) = index.notes.iter_mut().find(|note| note.id == id) {
-        if let Some(ref mut window_info) = entry.window {
-            window_info.x = x;
-            window_info.y = y;
-            window_info.width = width;
-            window_info.height = height;
-        } else {
-            // 如果窗口信息不存在，创建一个新的
-            entry.window = Some(WindowInfo {
-                x,
-                y,
-                width,
-                height,
-            });
+    for label in hidden_labels {
+        if let Some(note_window) = app_handle.get_webview_window(&label) {
+            let _ = note_window.show();
         }
-        
-        // 保存更新后的索引
-        let json_content = serde_json::to_string_pretty(&index)
-            .map_err(|e| format!("序列化索引失败: {}", e))?;
-        write_file_safely(&index_path, json_content)
-            .map_err(|e| format!("写入索引文件失败: {}", e))?;
-
-        Ok(())
-    } else {
-        Err("找不到指定的便签".to_string())
     }
+    Ok(())
 }
 
 // 新增创建窗口的命令
@@ -1314,18 +5060,46 @@ async fn create_note_window(
     x: Option<i32>,
     y: Option<i32>,
 ) -> Result<(), String> {
+    // 从索引中读取该便签的窗口选项（系统标题栏、所有桌面可见），默认都关闭
+    let note_id = label.replace("note-", "");
+    let stored_window_info = get_app_data_dir().ok()
+        .and_then(|app_data_dir| read_index(&app_data_dir).ok())
+        .and_then(|index| index.notes.into_iter().find(|note| note.id == note_id))
+        .and_then(|note| note.window);
+    let decorated = stored_window_info.as_ref().is_some_and(|window_info| window_info.decorated);
+    let on_all_desktops = supports_all_desktops()
+        && stored_window_info.as_ref().is_some_and(|window_info| window_info.on_all_desktops);
+    // 绑定显示器的便签按该显示器的当前位置还原
+    let (x, y) = match stored_window_info.as_ref().filter(|window_info| window_info.monitor_index.is_some()) {
+        Some(window_info) => {
+            let (global_x, global_y) = to_global_position(&app_handle, window_info.monitor_index, window_info.x, window_info.y);
+            (Some(global_x as i32), Some(global_y as i32))
+        }
+        None => (x, y),
+    };
+    // 保存的位置已不在任何显示器上时挪回主显示器
+    let (x, y) = match (x, y) {
+        (Some(x), Some(y)) => {
+            let (x, y) = clamp_to_visible_monitor(&app_handle, x as f64, y as f64, width as f64, height as f64);
+            (Some(x as i32), Some(y as i32))
+        }
+        _ => (x, y),
+    };
+
     let window = tauri::WebviewWindowBuilder::new(
         &app_handle,
         &label,
-        tauri::WebviewUrl::App(format!("index.html?noteId={}", &label.replace("note-", "")).into()),
+        tauri::WebviewUrl::App(format!("index.html?noteId={}", &note_id).into()),
     )
     .title(&title)
-    .inner_size(width as f64, height as f64)
+    .inner_size((width as f64).max(NOTE_MIN_WIDTH), (height as f64).max(NOTE_MIN_HEIGHT))
+    .min_inner_size(NOTE_MIN_WIDTH, NOTE_MIN_HEIGHT)
     .resizable(true)
-    .decorations(false)
+    .decorations(decorated)
     .maximizable(false)
     .transparent(false)
     .always_on_top(false)
+    .visible_on_all_workspaces(on_all_desktops)
     .visible(true);
 
     let _window = if let (Some(x_pos), Some(y_pos)) = (x, y) {
@@ -1383,16 +5157,228 @@ async fn get_schedule_settings() -> Result<ScheduleSettings, String> {
     Ok(load_schedule_settings_from_disk())
 }
 
+#[tauri::command]
+async fn get_default_note_geometry() -> Result<WindowInfo, String> {
+    let settings = load_schedule_settings_from_disk();
+    Ok(WindowInfo {
+        x: settings.default_note_x.unwrap_or(DEFAULT_NOTE_X),
+        y: settings.default_note_y.unwrap_or(DEFAULT_NOTE_Y),
+        width: settings.default_note_width.unwrap_or(DEFAULT_NOTE_WIDTH),
+        height: settings.default_note_height.unwrap_or(DEFAULT_NOTE_HEIGHT),
+        decorated: false,
+        on_all_desktops: false,
+        monitor_index: None,
+    })
+}
+
+// 传入 None 表示清除该项，回退到内置默认值
+#[tauri::command]
+async fn set_default_note_geometry(
+    x: Option<f64>,
+    y: Option<f64>,
+    width: Option<f64>,
+    height: Option<f64>,
+    cascade: bool,
+) -> Result<(), String> {
+    let mut settings = load_schedule_settings_from_disk();
+    settings.default_note_x = x;
+    settings.default_note_y = y;
+    settings.default_note_width = width.map(|value| value.max(NOTE_MIN_WIDTH));
+    settings.default_note_height = height.map(|value| value.max(NOTE_MIN_HEIGHT));
+    settings.cascade = cascade;
+    save_schedule_settings_to_disk(&settings)
+}
+
+// 通过文件夹选择对话框切换数据目录：目标目录没有 index.json 时复制当前数据过去（原目录保留作备份），
+// 已有 index.json 时直接使用。用户取消选择时返回 None，不做任何修改
+#[tauri::command]
+async fn choose_data_directory(window: tauri::WebviewWindow) -> Result<Option<String>, String> {
+    let app_handle = window.app_handle().clone();
+    let picked = app_handle.dialog()
+        .file()
+        .set_title("选择 FadeNote 数据目录")
+        .blocking_pick_folder();
+    let target_dir = match picked {
+        Some(path) => path.into_path().map_err(|e| format!("无效的目录: {}", e))?,
+        None => return Ok(None),
+    };
+
+    if !target_dir.is_dir() {
+        return Err("选择的路径不是文件夹".to_string());
+    }
+    let current_dir = get_app_data_dir()?;
+    if target_dir == current_dir {
+        return Ok(Some(target_dir.to_string_lossy().to_string()));
+    }
+    if target_dir.starts_with(&current_dir) {
+        return Err("不能选择当前数据目录内的文件夹".to_string());
+    }
+
+    // 确认目标目录可写
+    let probe_path = target_dir.join(format!(".fadenote-{}.tmp", Uuid::new_v4()));
+    fs::write(&probe_path, b"").map_err(|e| format!("目标目录不可写: {}", e))?;
+    let _ = fs::remove_file(&probe_path);
+
+    if !target_dir.join("index.json").exists() {
+        for file_name in ["index.json", "settings.json"] {
+            let source = current_dir.join(file_name);
+            if source.exists() {
+                fs::copy(&source, target_dir.join(file_name))
+                    .map_err(|e| format!("复制 {} 失败: {}", file_name, e))?;
+            }
+        }
+        let current_notes = current_dir.join("notes");
+        if current_notes.exists() {
+            copy_dir_recursive(&current_notes, &target_dir.join("notes"))?;
+        }
+    }
+
+    set_custom_data_dir(&target_dir)?;
+    {
+        let app_state = app_handle.state::<AppState>();
+        let mut dir_lock = app_state.notes_directory.lock().unwrap();
+        *dir_lock = Some(target_dir.clone());
+    }
+    // 设置已指向新目录，建目录和校验索引时读取的是新目录下的设置
+    initialize_notes_directory_by_path(target_dir.clone()).await?;
+    emit_index_changed(&app_handle, "updated", Vec::new());
+
+    info!(path = %target_dir.display(), "切换数据目录");
+    Ok(Some(target_dir.to_string_lossy().to_string()))
+}
+
+// 返回索引中的安装信息及派生统计，只读不写
+#[tauri::command]
+async fn app_info(window: tauri::WebviewWindow) -> Result<AppInfoSummary, String> {
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    let app = read_index(&notes_dir)?.app;
+
+    let install_age_days = DateTime::parse_from_rfc3339(&app.created_at)
+        .ok()
+        .map(|created_time| (Local::now() - created_time.with_timezone(&Local)).num_days().max(0));
+    Ok(AppInfoSummary {
+        has_rebuilt: app.rebuild_at.is_some(),
+        install_age_days,
+        name: app.name,
+        created_at: app.created_at,
+        rebuild_at: app.rebuild_at,
+    })
+}
+
+// 返回索引格式版本，供前端提示兼容性问题
+#[tauri::command]
+async fn get_index_version(window: tauri::WebviewWindow) -> Result<u32, String> {
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    Ok(read_index(&notes_dir)?.version)
+}
+
+// 返回索引中原样保存的 app 信息块
+#[tauri::command]
+async fn get_app_info(window: tauri::WebviewWindow) -> Result<AppInfo, String> {
+    let notes_dir = PathBuf::from(ensure_notes_directory(window).await?);
+    Ok(read_index(&notes_dir)?.app)
+}
+
 #[tauri::command]
 async fn get_app_data_directory() -> Result<String, String> {
     Ok(get_app_data_dir()?.to_string_lossy().to_string())
 }
 
+// 在系统文件管理器中打开数据目录；目录尚未创建时返回错误
+#[tauri::command]
+async fn open_data_directory() -> Result<(), String> {
+    let app_data_dir = get_app_data_dir()?;
+    if !app_data_dir.is_dir() {
+        return Err("数据目录尚未创建".to_string());
+    }
+    tauri_plugin_opener::open_path(&app_data_dir, None::<&str>)
+        .map_err(|e| format!("打开数据目录失败: {}", e))
+}
+
+#[tauri::command]
+async fn save_schedule_settings(app_handle: tauri::AppHandle, mut settings: ScheduleSettings) -> Result<(), String> {
+    // 加密相关字段只能通过 set_encryption_passphrase 修改，避免前端用旧设置覆盖后无法解密
+    let stored_settings = load_schedule_settings_from_disk();
+    settings.encryption_salt = stored_settings.encryption_salt;
+    settings.encryption_verifier = stored_settings.encryption_verifier;
+    save_schedule_settings_to_disk(&settings)?;
+    // 语言可能随设置一起变化，同步刷新托盘菜单
+    refresh_tray_menu(&app_handle);
+    Ok(())
+}
+
+// 设置并持久化界面语言（"system" / "en" / "zh-CN"），同时重建托盘菜单
+#[tauri::command]
+async fn set_language(app_handle: tauri::AppHandle, language: String) -> Result<(), String> {
+    let mut settings = load_schedule_settings_from_disk();
+    settings.language = language.clone();
+    save_schedule_settings_to_disk(&settings)?;
+    refresh_tray_menu(&app_handle);
+    let _ = app_handle.emit("fadenote://language-changed", language);
+    Ok(())
+}
+
+// 最近的错误日志（从旧到新），让前端能显示原本只写进日志的失败
+#[tauri::command]
+async fn get_recent_errors(window: tauri::WebviewWindow) -> Result<Vec<RecentError>, String> {
+    let app_state = window.state::<AppState>();
+    let errors = app_state.recent_errors.lock().unwrap();
+    Ok(errors.iter().cloned().collect())
+}
+
+// 查询当前是否已在系统中注册登录自启动
+#[tauri::command]
+async fn get_autostart(app_handle: tauri::AppHandle) -> Result<bool, String> {
+    app_handle.autolaunch().is_enabled()
+        .map_err(|e| format!("查询自启动状态失败: {}", e))
+}
+
+// 立即在系统中注册 / 取消登录自启动，成功后再保存到设置
 #[tauri::command]
-async fn save_schedule_settings(settings: ScheduleSettings) -> Result<(), String> {
+async fn set_autostart(app_handle: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    let autolaunch = app_handle.autolaunch();
+    if enabled {
+        autolaunch.enable()
+    } else {
+        autolaunch.disable()
+    }.map_err(|e| format!("设置自启动失败: {}", e))?;
+
+    let mut settings = load_schedule_settings_from_disk();
+    settings.autostart = enabled;
     save_schedule_settings_to_disk(&settings)
 }
 
+// 按当前语言设置构建托盘菜单
+fn build_tray_menu(app_handle: &tauri::AppHandle) -> tauri::Result<Menu<tauri::Wry>> {
+    let labels = tray_labels(&load_schedule_settings_from_disk().language);
+    let new_note_item = MenuItem::with_id(app_handle, "new_note", labels.new_note, true, None::<&str>)?;
+    let show_notes_item = MenuItem::with_id(app_handle, "show_notes", labels.show_notes, true, None::<&str>)?;
+    let settings_item = MenuItem::with_id(app_handle, "settings", labels.settings, true, None::<&str>)?;
+    let archive_item = MenuItem::with_id(app_handle, "archive", labels.archive, true, None::<&str>)?;
+    let quit_item = MenuItem::with_id(app_handle, "quit", labels.quit, true, None::<&str>)?;
+
+    MenuBuilder::new(app_handle)
+        .item(&new_note_item)
+        .item(&show_notes_item)
+        .separator()
+        .item(&settings_item)
+        .item(&archive_item)
+        .separator()
+        .item(&quit_item)
+        .build()
+}
+
+fn refresh_tray_menu(app_handle: &tauri::AppHandle) {
+    if let Some(tray) = app_handle.tray_by_id(TRAY_ID) {
+        match build_tray_menu(app_handle) {
+            Ok(menu) => {
+                let _ = tray.set_menu(Some(menu));
+            }
+            Err(e) => error!(error = %e, "重建托盘菜单失败"),
+        }
+    }
+}
+
 #[tauri::command]
 async fn raise_active_notes_once(app_handle: tauri::AppHandle) -> Result<(), String> {
     raise_active_notes_once_impl(app_handle).await
@@ -1415,6 +5401,7 @@ pub async fn initialize_notes_directory_by_path(notes_dir: std::path::PathBuf) -
 
 // 创建新的便签（通过路径）
 pub async fn create_note_by_path(notes_dir: std::path::PathBuf, x: f64, y: f64, width: f64, height: f64) -> Result<String, String> {
+    let settings = load_schedule_settings_from_disk();
     // 生成UUID作为ID
     let id = Uuid::new_v4().to_string();
     
@@ -1423,11 +5410,10 @@ pub async fn create_note_by_path(notes_dir: std::path::PathBuf, x: f64, y: f64,
     let expires_at = expire_at_7_days_from_iso(&created_at)?;
     
     // 创建文件内容
-    let content = build_full_content(&id, &created_at, "");
+    let content = build_full_content(&id, &created_at, "", front_matter_style(&settings));
     
     // 创建按日期组织的目录结构
-    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
-    let dated_dir = notes_dir.join("notes").join(today);
+    let dated_dir = dated_note_dir(&notes_dir, Local::now().date_naive(), &settings);
     std::fs::create_dir_all(&dated_dir).map_err(|e| format!("创建日期目录失败: {}", e))?;
 
     // 创建文件
@@ -1450,27 +5436,23 @@ pub async fn create_note_by_path(notes_dir: std::path::PathBuf, x: f64, y: f64,
         .to_string_lossy()
         .to_string();
 
+    // 初始last_active_at就是创建时间
     let mut new_entry = NoteEntry {
-        id: id.clone(),
-        created_at: created_at.clone(),
-        last_active_at: created_at.clone(), // 初始last_active_at就是创建时间
         expire_at: Some(expires_at.clone()),
-        cached_preview: None,
-        status: String::new(), // 禁止手写，将在派生时设置
-        archived_at: None,
-        window: Some(WindowInfo {
+        cached_preview: note_preview("", &settings),
+        ..NoteEntry::new(id.clone(), created_at.clone(), rel_path, Some(WindowInfo {
             x,
             y,
             width,
             height,
-        }),
-        pinned: false,  // 默认不固定
-        file: FileInfo {
-            relative_path: rel_path,
-        },
+            decorated: false,
+            on_all_desktops: false,
+            monitor_index: None,
+        }))
     };
     
     // 派生状态
+    record_note_event(&mut new_entry, "created", &created_at);
     derive_status(&mut new_entry);
     
     index.notes.push(new_entry);
@@ -1485,6 +5467,7 @@ pub async fn create_note_by_path(notes_dir: std::path::PathBuf, x: f64, y: f64,
 
 // 更新便签的窗口信息到index.json
 async fn update_note_window_info(
+    app_handle: &tauri::AppHandle,
     app_data_dir: &Path,
     note_id: &str,
     x: f64,
@@ -1508,12 +5491,23 @@ async fn update_note_window_info(
     let mut found = false;
     for entry in &mut index.notes {
         if entry.id == note_id {
-            entry.window = Some(WindowInfo {
-                x,
-                y,
-                width,
-                height,
-            });
+            if let Some(ref mut window_info) = entry.window {
+                let (x, y) = to_stored_position(app_handle, window_info.monitor_index, x, y);
+                window_info.x = x;
+                window_info.y = y;
+                window_info.width = width;
+                window_info.height = height;
+            } else {
+                entry.window = Some(WindowInfo {
+                    x,
+                    y,
+                    width,
+                    height,
+                    decorated: false,
+                    on_all_desktops: false,
+                    monitor_index: None,
+                });
+            }
             found = true;
             break;
         }
@@ -1540,18 +5534,66 @@ async fn has_unexpired_notes(window: tauri::WebviewWindow) -> Result<bool, Strin
 }
 
 fn main() {
+    // 日志级别在启动时读取，修改后下次启动生效
+    let _ = SESSION_STARTED_AT.set(Local::now());
+    let recent_errors = RecentErrors::default();
+    let _log_guard = get_app_data_dir().ok()
+        .and_then(|app_data_dir| init_logging(&app_data_dir, &load_schedule_settings_from_disk().log_level, recent_errors.clone()));
+
     tauri::Builder::default()
         .manage(AppState {
             notes_directory: Mutex::new(None),
+            last_action: Mutex::new(None),
+            zoomed_notes: Mutex::new(std::collections::HashMap::new()),
+            focus_hidden_labels: Mutex::new(Vec::new()),
+            startup_report: Mutex::new(None),
+            flush_waiters: Mutex::new(std::collections::HashMap::new()),
+            content_hashes: Mutex::new(std::collections::HashMap::new()),
+            temporary_pins: Mutex::new(std::collections::HashMap::new()),
+            slide_generations: Mutex::new(std::collections::HashMap::new()),
+            flashing_notes: Mutex::new(std::collections::HashMap::new()),
+            unlocked_notes: Mutex::new(std::collections::HashMap::new()),
+            encryption_key: Mutex::new(None),
+            minimized_labels: Mutex::new(std::collections::HashSet::new()),
+            closing_notes: Mutex::new(std::collections::HashSet::new()),
+            recent_errors,
         })
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_autostart::init(MacosLauncher::LaunchAgent, None))
         .on_window_event(|window, event| {
-            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
-                // 隐藏窗口而不是关闭它
-                let _ = window.hide();
-                // 阻止默认的关闭行为
-                api.prevent_close();
+            match event {
+                tauri::WindowEvent::CloseRequested { .. } if is_peek_label(window.label()) => {
+                    // 只读预览窗口直接关闭
+                }
+                tauri::WindowEvent::CloseRequested { api, .. } => {
+                    // 隐藏窗口而不是关闭它
+                    let _ = window.hide();
+                    // 阻止默认的关闭行为
+                    api.prevent_close();
+                    if let Some(id) = window.label().strip_prefix("note-") {
+                        if load_schedule_settings_from_disk().archive_on_close {
+                            archive_note_on_close(window.app_handle().clone(), id.to_string());
+                        }
+                    }
+                }
+                tauri::WindowEvent::Destroyed => {
+                    // 窗口销毁后取消尚未结束的闪现
+                    window.state::<AppState>().flashing_notes.lock().unwrap().remove(window.label());
+                    window.state::<AppState>().minimized_labels.lock().unwrap().remove(window.label());
+                }
+                tauri::WindowEvent::Resized(_) if window.label().starts_with("note-") => {
+                    // 系统最小化没有单独的事件，通过尺寸变化时的最小化状态跟踪
+                    let app_state = window.state::<AppState>();
+                    let mut minimized_labels = app_state.minimized_labels.lock().unwrap();
+                    if window.is_minimized().unwrap_or(false) {
+                        minimized_labels.insert(window.label().to_string());
+                    } else {
+                        minimized_labels.remove(window.label());
+                    }
+                }
+                _ => {}
             }
         })
         .invoke_handler(tauri::generate_handler![
@@ -1561,46 +5603,124 @@ fn main() {
             get_active_notes,
             get_all_active_notes,
             get_archived_notes,
+            get_archive_rows,
+            get_archived_previews,
+            peek_archived_window,
+            save_layout,
+            apply_layout,
+            get_folder_summary,
+            get_notes_by_date,
             get_notes_without_windows,
+            list_open_note_windows,
             restore_notes_without_windows,
+            set_note_tags,
+            set_tags_bulk,
+            quick_switch,
+            get_active_notes_by_urgency,
+            fade_progress,
+            focus_note,
+            hide_note,
+            minimize_note,
+            set_note_auto_hide_edge,
+            slide_note_window,
+            show_notes_with_tag,
+            hide_notes_with_tag,
+            storage_usage,
+            check_index,
+            export_index_json,
+            export_combined,
+            export_clean,
+            import_index_json,
+            import_notes,
+            get_orphan_files,
+            adopt_orphan,
+            repair_paths,
+            relink_note,
+            repair_front_matter,
             has_unexpired_notes,
             create_note,
+            create_note_from_clipboard,
+            create_notes_bulk,
+            snapshot_note,
             load_note,
+            render_note_html,
             update_note_activity,
+            touch_note,
+            get_note_plaintext,
+            note_content_hash,
+            freeze_fading,
+            unfreeze_fading,
             save_note_content,
+            save_all_now,
             save_note_content_without_touch,
             update_note_window,
+            toggle_note_zoom,
+            arrange_notes_grid,
+            arrange_windows,
+            flash_note,
+            focus_mode,
+            exit_focus_mode,
             restore_note,
+            archive_note_now,
+            restore_notes_in_range,
+            restore_last_archived,
+            get_notes_in_created_range,
+            reactivate_as_new,
+            undo_last_action,
+            take_startup_report,
+            set_note_created_at,
+            get_note_timeline,
+            set_note_slug,
+            reorganize_notes,
             set_note_pinned,
+            set_note_never_expire,
+            set_note_focus_on_launch,
+            set_note_fade_anchor,
+            pin_expiring_notes,
+            unpin_temporary,
+            set_note_font_size,
+            set_note_colors,
+            set_note_markdown,
+            set_note_reminder,
+            set_note_protected,
+            unlock_note,
+            set_encryption_passphrase,
+            set_note_decorated,
+            set_note_on_all_desktops,
+            set_note_monitor,
+            move_note_to_monitor,
             delete_note,
             create_archive_window,
             create_settings_window,
             get_schedule_settings,
             get_app_data_directory,
+            open_data_directory,
+            app_info,
+            get_index_version,
+            get_app_info,
+            choose_data_directory,
             save_schedule_settings,
+            set_language,
+            get_recent_errors,
+            get_autostart,
+            set_autostart,
+            get_default_note_geometry,
+            set_default_note_geometry,
             raise_active_notes_once
         ])
         .setup(|app| {
-            // 创建系统托盘菜单项
-            let new_note_item = MenuItem::with_id(app, "new_note", "New Note", true, None::<&str>).unwrap();
-            let show_notes_item = MenuItem::with_id(app, "show_notes", "Show Notes", true, None::<&str>).unwrap();
-            let settings_item = MenuItem::with_id(app, "settings", "Settings", true, None::<&str>).unwrap();
-            let archive_item = MenuItem::with_id(app, "archive", "Archive", true, None::<&str>).unwrap();
-            let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>).unwrap();
-            
+            // 开启自启动时重新注册一次，应用移动位置或重装后登录项仍指向当前的可执行文件
+            if load_schedule_settings_from_disk().autostart {
+                if let Err(e) = app.autolaunch().enable() {
+                    warn!(error = %e, "重新注册自启动失败");
+                }
+            }
+
             // 创建系统托盘菜单
-            let tray_menu = MenuBuilder::new(app)
-                .item(&new_note_item)
-                .item(&show_notes_item)
-                .separator()
-                .item(&settings_item)
-                .item(&archive_item)
-                .separator()
-                .item(&quit_item)
-                .build().unwrap();
+            let tray_menu = build_tray_menu(app.handle()).unwrap();
             
             // 创建托盘图标（注意：Windows 必须提供 icon）
-            let _tray = TrayIconBuilder::new()
+            let _tray = TrayIconBuilder::with_id(TRAY_ID)
                 .icon(app.default_window_icon().unwrap().clone()) // 使用窗口图标
                 .menu(&tray_menu)
                 .on_menu_event(|_app, event| {
@@ -1609,20 +5729,31 @@ fn main() {
                             // 创建新便签
                             let app_handle = _app.clone();
                             tauri::async_runtime::spawn(async move {
+                                let app_data_dir = match get_app_data_dir() {
+                                    Ok(dir) => dir,
+                                    Err(e) => {
+                                        error!(error = %e, "获取数据目录失败");
+                                        return;
+                                    }
+                                };
+                                // 从设置中读取默认位置和大小
+                                let window_info = default_note_window_info(&app_data_dir);
+
                                 // 创建新便签
                                 let id = match create_note_by_path(
-                                    get_app_data_dir().unwrap(),
-                                    200.0,  // 默认X坐标
-                                    200.0,  // 默认Y坐标
-                                    280.0,  // 默认宽度
-                                    360.0,  // 默认高度
+                                    app_data_dir,
+                                    window_info.x,
+                                    window_info.y,
+                                    window_info.width,
+                                    window_info.height,
                                 ).await {
                                     Ok(id) => id,
                                     Err(e) => {
-                                        eprintln!("创建新便签失败: {}", e);
+                                        error!(error = %e, "创建新便签失败");
                                         return;
                                     }
                                 };
+                                emit_index_changed(&app_handle, "created", vec![id.clone()]);
                                 
                                 // 为新便签创建窗口
                                 let label = format!("note-{}", id);
@@ -1630,12 +5761,12 @@ fn main() {
                                     app_handle.clone(),
                                     label,
                                     "FadeNote".to_string(),
-                                    280,
-                                    360,
-                                    Some(200),
-                                    Some(200),
+                                    window_info.width as u32,
+                                    window_info.height as u32,
+                                    Some(window_info.x as i32),
+                                    Some(window_info.y as i32),
                                 ).await {
-                                    eprintln!("创建便签窗口失败: {}", e);
+                                    error!(error = %e, "创建便签窗口失败");
                                 }
                             });
                         },
@@ -1643,17 +5774,23 @@ fn main() {
                             // 恢复没有窗口或隐藏的便签
                             let app_handle = _app.clone();
                             tauri::async_runtime::spawn(async move {
-                                println!("Show Notes 功能被调用");
+                                debug!("Show Notes 功能被调用");
                                 
                                 // 获取当前所有窗口及其可见性状态
                                 let all_windows = app_handle.webview_windows();
-                                println!("当前窗口数量: {}", all_windows.len());
+                                debug!(count = all_windows.len(), "当前窗口数量");
                                 
                                 // 获取所有活跃便签
-                                let app_data_dir = get_app_data_dir().unwrap();
+                                let app_data_dir = match get_app_data_dir() {
+                                    Ok(dir) => dir,
+                                    Err(e) => {
+                                        error!(error = %e, "获取数据目录失败");
+                                        return;
+                                    }
+                                };
                                 let index = validate_and_fix_index(&app_data_dir).unwrap_or_else(|_| new_empty_index());
                                 
-                                println!("索引中便签总数: {}", index.notes.len());
+                                debug!(count = index.notes.len(), "索引中便签总数");
                                 
                                 let mut active_count = 0;
                                 let mut window_null_count = 0;
@@ -1667,40 +5804,44 @@ fn main() {
                                         }
                                         
                                         let label = format!("note-{}", entry.id);
-                                        println!("处理便签 {}: window.is_none() = {}, 标签 = {}", 
-                                                entry.id, entry.window.is_none(), label);
+                                        debug!(note_id = %entry.id, window_missing = entry.window.is_none(), label = %label, "处理便签");
                                         
                                         // 检查窗口是否存在且是否可见
                                         if let Some(note_window) = all_windows.get(&label) {
-                                            println!("便签 {} 窗口已存在", entry.id);
+                                            debug!(note_id = %entry.id, "便签窗口已存在");
                                             // 窗口存在，检查是否可见
                                             if let Ok(is_visible) = note_window.is_visible() {
-                                                if !is_visible {
+                                                if is_note_window_minimized(note_window) {
+                                                    info!(note_id = %entry.id, "还原最小化的窗口");
+                                                    let _ = note_window.show();
+                                                    let _ = note_window.unminimize();
+                                                    let _ = note_window.set_focus();
+                                                } else if !is_visible {
                                                     // 窗口存在但不可见，显示它
-                                                    println!("显示隐藏的窗口: {}", entry.id);
+                                                    info!(note_id = %entry.id, "显示隐藏的窗口");
                                                     let _ = note_window.show();
                                                     let _ = note_window.set_focus();
                                                 } else {
-                                                    println!("窗口 {} 已经可见", entry.id);
+                                                    debug!(note_id = %entry.id, "窗口已经可见");
                                                 }
                                             } else {
                                                 // 无法获取可见性，尝试显示
-                                                println!("无法获取可见性，尝试显示窗口: {}", entry.id);
+                                                warn!(note_id = %entry.id, "无法获取可见性，尝试显示窗口");
                                                 let _ = note_window.show();
                                                 let _ = note_window.set_focus();
                                             }
                                         } else {
                                             // 窗口不存在，创建新窗口
-                                            println!("创建新窗口 for 便签: {}", entry.id);
+                                            info!(note_id = %entry.id, "创建新窗口");
                                             // 如果有窗口配置，使用配置信息；否则使用默认配置
                                             let (width, height, x, y) = if let Some(window_info) = entry.window.as_ref() {
                                                 (window_info.width as u32, window_info.height as u32, 
                                                  Some(window_info.x as i32), Some(window_info.y as i32))
                                             } else {
-                                                // 为没有窗口配置的便签使用默认配置，并添加基于ID的偏移
-                                                println!("使用默认窗口配置 for 便签: {}", entry.id);
-                                                let offset = (entry.id.as_bytes()[0] as i32 * 30) % 300; // 基于ID生成偏移量
-                                                (280u32, 360u32, Some(100i32 + offset), Some(100i32 + offset))
+                                                // 为没有窗口配置的便签使用默认大小和基于ID的默认位置
+                                                debug!(note_id = %entry.id, "使用默认窗口配置");
+                                                let (default_x, default_y) = default_window_position(&app_handle, &entry.id);
+                                                (DEFAULT_NOTE_WIDTH as u32, DEFAULT_NOTE_HEIGHT as u32, Some(default_x as i32), Some(default_y as i32))
                                             };
                                             
                                             if let Err(e) = create_note_window(
@@ -1712,16 +5853,16 @@ fn main() {
                                                 x,
                                                 y,
                                             ).await {
-                                                eprintln!("恢复便签窗口失败 {}: {}", entry.id, e);
+                                                error!(note_id = %entry.id, error = %e, "恢复便签窗口失败");
                                             } else {
-                                                println!("成功创建窗口 for 便签: {}", entry.id);
+                                                info!(note_id = %entry.id, "成功创建窗口");
                                                 
                                                 // 更新index.json中的window属性
                                                 if entry.window.is_none() {
-                                                    if let Err(e) = update_note_window_info(&app_data_dir, &entry.id, x.unwrap_or(100) as f64, y.unwrap_or(100) as f64, width as f64, height as f64).await {
-                                                        eprintln!("更新便签窗口信息失败 {}: {}", entry.id, e);
+                                                    if let Err(e) = update_note_window_info(&app_handle, &app_data_dir, &entry.id, x.unwrap_or(100) as f64, y.unwrap_or(100) as f64, width as f64, height as f64).await {
+                                                        error!(note_id = %entry.id, error = %e, "更新便签窗口信息失败");
                                                     } else {
-                                                        println!("成功更新便签 {} 的窗口信息到index.json", entry.id);
+                                                        debug!(note_id = %entry.id, "成功更新便签的窗口信息到index.json");
                                                     }
                                                 }
                                             }
@@ -1729,8 +5870,7 @@ fn main() {
                                     }
                                 }
                                 
-                                println!("处理完成: 活跃便签 {} 个，其中 window 为 null 的有 {} 个", 
-                                        active_count, window_null_count);
+                                info!(active_count, window_null_count, "处理完成");
                             });
                         },
                         "archive" => {
@@ -1750,8 +5890,9 @@ fn main() {
                             // 退出前确保所有状态持久化
                             tauri::async_runtime::spawn(async move {
                                 // 确保index.json是最新的
-                                let app_data_dir = get_app_data_dir().unwrap();
-                                let _ = validate_and_fix_index(&app_data_dir);
+                                if let Ok(app_data_dir) = get_app_data_dir() {
+                                    let _ = validate_and_fix_index(&app_data_dir);
+                                }
                                 
                                 // 安全退出
                                 std::process::exit(0);
@@ -1769,11 +5910,11 @@ fn main() {
                     let now = Local::now();
                     if let Some(trigger_key) = should_trigger_schedule(&settings, &now) {
                         if let Err(e) = raise_active_notes_once_impl(scheduler_app_handle.clone()).await {
-                            eprintln!("scheduled raise failed: {}", e);
+                            error!(error = %e, "scheduled raise failed");
                         }
                         settings.last_triggered_key = Some(trigger_key);
                         if let Err(e) = save_schedule_settings_to_disk(&settings) {
-                            eprintln!("save scheduled raise record failed: {}", e);
+                            error!(error = %e, "save scheduled raise record failed");
                         }
                     }
                     std::thread::sleep(StdDuration::from_secs(30));
@@ -1785,44 +5926,83 @@ fn main() {
             tauri::async_runtime::spawn(async move {
                 loop {
                     if let Err(e) = run_lifecycle_pass(lifecycle_app_handle.clone()).await {
-                        eprintln!("lifecycle pass failed: {}", e);
+                        error!(error = %e, "lifecycle pass failed");
                     }
+                    clear_expired_undo_action(&lifecycle_app_handle);
                     std::thread::sleep(StdDuration::from_secs(60));
                 }
             });
 
+            // 拿不到系统数据目录时提示用户，便签会临时保存在临时目录中
+            if system_app_data_dir().is_none() {
+                app.dialog()
+                    .message("无法获取系统数据目录，便签将临时保存在临时目录中，重启后可能丢失。")
+                    .title("FadeNote")
+                    .kind(MessageDialogKind::Error)
+                    .show(|_| {});
+            }
+
             tauri::async_runtime::block_on(async {
                 // 获取应用数据目录
-                let app_data_dir = get_app_data_dir().unwrap();
+                let app_data_dir = match get_app_data_dir() {
+                    Ok(dir) => dir,
+                    Err(e) => {
+                        error!(error = %e, "获取数据目录失败");
+                        return;
+                    }
+                };
                 // 确保目录存在
-                std::fs::create_dir_all(&app_data_dir).unwrap();
+                if let Err(e) = std::fs::create_dir_all(&app_data_dir) {
+                    error!(path = %app_data_dir.display(), error = %e, "创建数据目录失败");
+                    app.dialog()
+                        .message(format!("无法创建数据目录 {}：{}", app_data_dir.display(), e))
+                        .title("FadeNote")
+                        .kind(MessageDialogKind::Error)
+                        .show(|_| {});
+                    return;
+                }
                 
                 // 检查是否为首次启动
                 let first_launch = is_first_launch(&app_data_dir);
+                let index_before = read_index(&app_data_dir).ok();
                 
                 // 验证并修复索引
                 match validate_and_fix_index(&app_data_dir) {
                     Ok(_) => {
-                        println!("成功初始化便签目录: {}", app_data_dir.display());
+                        info!(path = %app_data_dir.display(), "成功初始化便签目录");
                         
                         // Fix 6: 启动流程遵循正确顺序
                         // 1. Load index
                         let mut index = match validate_and_fix_index(&app_data_dir) {
                             Ok(idx) => idx,
                             Err(e) => {
-                                eprintln!("验证和修复索引失败: {}", e);
+                                error!(error = %e, "验证和修复索引失败");
                                 new_empty_index()
                             }
                         };
+
+                        // 启动检查摘要：有异常时通知前端（首次启动的重建不算）
+                        let report = build_startup_report(&app_data_dir, index_before.as_ref(), &index);
+                        if !first_launch && report.is_notable() {
+                            info!(rebuilt = report.rebuilt, adopted = ?report.adopted, missing_files = ?report.missing_files, "启动检查发现异常");
+                            *app.state::<AppState>().startup_report.lock().unwrap() = Some(report.clone());
+                            let _ = app.app_handle().emit("fadenote://startup-report", report);
+                        }
                         
+                        let startup_settings = load_schedule_settings_from_disk();
+
                         // 2. Apply expire pass 已在 validate_and_fix_index 内执行
-                        // 3. Save index
-                        let index_path = app_data_dir.join("index.json");
-                        if let Ok(json_content) = serde_json::to_string_pretty(&index) {
-                            if let Err(e) = write_file_safely(&index_path, json_content) {
-                                eprintln!("保存索引失败: {}", e);
+                        // 按活跃便签上限归档最久未活动的便签
+                        if let Some(max_active) = startup_settings.max_active_notes {
+                            let evicted_ids = enforce_active_note_cap(&mut index, max_active, None, &Local::now());
+                            if !evicted_ids.is_empty() {
+                                info!(note_ids = ?evicted_ids, "超出活跃便签上限，自动归档");
                             }
                         }
+                        // 3. Save index
+                        if let Err(e) = save_index(&app_data_dir, &mut index) {
+                            error!(error = %e, "保存索引失败");
+                        }
                         
                         // 4. Get active notes for restoration
                         let mut active_notes = Vec::new();
@@ -1834,8 +6014,12 @@ fn main() {
                                                 
                         let unexpired_notes = active_notes;
                                                  
+                        // 关闭启动恢复时只加载索引，不创建任何便签窗口（首次启动的欢迎便签除外）
                         let mut restored_count = 0;
-                        if !unexpired_notes.is_empty() {
+                        let focus_on_launch_id = unexpired_notes.iter()
+                            .find(|note| note.focus_on_launch)
+                            .map(|note| note.id.clone());
+                        if startup_settings.restore_windows_on_startup && !unexpired_notes.is_empty() {
                             // 如果有未过期的便签，恢复它们的窗口
                             for note in unexpired_notes {
                                 if is_active(&note) && note.window.is_some() { // note是owned value，&note取引用
@@ -1854,10 +6038,10 @@ fn main() {
                                         Some(window_info.y as i32),
                                     ).await {
                                         Ok(_) => {
-                                            println!("恢复便签窗口: {}", note.id);
+                                            info!(note_id = %note.id, "恢复便签窗口");
                                             restored_count += 1;
                                         },
-                                        Err(e) => eprintln!("创建便签窗口失败 {}: {}", note.id, e),
+                                        Err(e) => error!(note_id = %note.id, error = %e, "创建便签窗口失败"),
                                     }
                                 }
                             }
@@ -1865,7 +6049,7 @@ fn main() {
                         
                         // 首次启动逻辑
                         if first_launch {
-                            println!("首次启动，创建欢迎便签");
+                            info!("首次启动，创建欢迎便签");
                             
                             // 创建欢迎便签
                             let welcome_id = Uuid::new_v4().to_string();
@@ -1875,17 +6059,19 @@ fn main() {
                             
                             // 创建欢迎内容
                             let welcome_content = get_welcome_content();
-                            let full_content = build_full_content(&welcome_id, &created_at, &welcome_content);
+                            let full_content = build_full_content(&welcome_id, &created_at, &welcome_content, front_matter_style(&startup_settings));
                             
                             // 创建按日期组织的目录结构
-                            let today = chrono::Local::now().format("%Y-%m-%d").to_string();
-                            let dated_dir = app_data_dir.join("notes").join(today);
-                            std::fs::create_dir_all(&dated_dir).unwrap();
+                            let dated_dir = dated_note_dir(&app_data_dir, Local::now().date_naive(), &startup_settings);
+                            if let Err(e) = std::fs::create_dir_all(&dated_dir) {
+                                error!(path = %dated_dir.display(), error = %e, "创建日期目录失败");
+                                return;
+                            }
 
                             // 创建文件
                             let file_path = dated_dir.join(format!("{}.md", welcome_id));
                             if let Err(e) = write_file_safely(&file_path, full_content) {
-                                eprintln!("创建欢迎便签文件失败 {}: {}", welcome_id, e);
+                                error!(note_id = %welcome_id, error = %e, "创建欢迎便签文件失败");
                                 return;
                             }
 
@@ -1895,34 +6081,27 @@ fn main() {
                                 .to_string();
 
                             let mut welcome_entry = NoteEntry {
-                                id: welcome_id.clone(),
-                                created_at: created_at.clone(),
-                                last_active_at: created_at.clone(),
                                 expire_at: Some(expires_at.clone()),
-                                cached_preview: Some("Write something...".to_string()),
-                                status: String::new(),
-                                archived_at: None,
-                                window: Some(WindowInfo {
+                                cached_preview: note_preview(&welcome_content, &startup_settings),
+                                ..NoteEntry::new(welcome_id.clone(), created_at.clone(), rel_path, Some(WindowInfo {
                                     x: 200.0,
                                     y: 200.0,
                                     width: 300.0,
                                     height: 380.0,
-                                }),
-                                pinned: false,  // 欢迎便签默认不固定
-                                file: FileInfo {
-                                    relative_path: rel_path,
-                                },
+                                    decorated: false,
+                                    on_all_desktops: false,
+                                    monitor_index: None,
+                                }))
                             };
                             
                             // 派生状态
+                            record_note_event(&mut welcome_entry, "created", &created_at);
                             derive_status(&mut welcome_entry);
                             index.notes.push(welcome_entry);
 
                             // 保存索引
-                            let json_content = serde_json::to_string_pretty(&index)
-                                .unwrap_or_else(|_| "{}".to_string());
-                            if let Err(e) = write_file_safely(&index_path, json_content) {
-                                eprintln!("保存欢迎便签索引失败 {}: {}", welcome_id, e);
+                            if let Err(e) = save_index(&app_data_dir, &mut index) {
+                                error!(note_id = %welcome_id, error = %e, "保存欢迎便签索引失败");
                                 return;
                             }
                             
@@ -1940,21 +6119,26 @@ fn main() {
                                 Some(200),
                             ).await {
                                 Ok(_) => {
-                                    println!("创建欢迎便签窗口: {}", welcome_id);
+                                    info!(note_id = %welcome_id, "创建欢迎便签窗口");
                                 },
-                                Err(e) => eprintln!("创建欢迎便签窗口失败 {}: {}", welcome_id, e),
+                                Err(e) => error!(note_id = %welcome_id, error = %e, "创建欢迎便签窗口失败"),
                             }
                         }
-                        // 如果不是首次启动且没有恢复任何窗口，创建默认便签
-                        else if restored_count == 0 {
+                        // 非首次启动时按设置决定是否创建默认便签
+                        else if startup_settings.restore_windows_on_startup && match startup_settings.startup_behavior {
+                            StartupBehavior::RestoreThenNewIfEmpty => restored_count == 0 && startup_settings.create_blank_note_when_empty,
+                            StartupBehavior::RestoreOnly => false,
+                            StartupBehavior::AlwaysNew => true,
+                        } {
                             // 直接创建便签和窗口，而不使用临时窗口
                             // 创建便签
-                            let index_path = app_data_dir.join("index.json");
-                            let mut index: IndexFile = if index_path.exists() {
-                                let content = std::fs::read_to_string(&index_path).unwrap_or_else(|_| "{}".to_string());
-                                serde_json::from_str(&content).unwrap_or_else(|_| new_empty_index())
-                            } else {
-                                new_empty_index()
+                            // 索引无法读取（含版本过新）时不创建，避免用空索引覆盖
+                            let mut index = match read_index_or_empty(&app_data_dir) {
+                                Ok(index) => index,
+                                Err(e) => {
+                                    error!(error = %e, "读取索引失败，跳过创建默认便签");
+                                    return;
+                                }
                             };
                             
                             // 生成UUID作为ID
@@ -1967,17 +6151,19 @@ fn main() {
                                 .unwrap_or_else(|_| (Local::now() + Duration::days(7)).to_rfc3339());
                             
                             // 创建文件内容
-                            let content = build_full_content(&id, &created_at, "");
+                            let content = build_full_content(&id, &created_at, "", front_matter_style(&startup_settings));
                             
                             // 创建按日期组织的目录结构
-                            let today = chrono::Local::now().format("%Y-%m-%d").to_string();
-                            let dated_dir = app_data_dir.join("notes").join(today);
-                            std::fs::create_dir_all(&dated_dir).unwrap();
+                            let dated_dir = dated_note_dir(&app_data_dir, Local::now().date_naive(), &startup_settings);
+                            if let Err(e) = std::fs::create_dir_all(&dated_dir) {
+                                error!(path = %dated_dir.display(), error = %e, "创建日期目录失败");
+                                return;
+                            }
 
                             // 创建文件
                             let file_path = dated_dir.join(format!("{}.md", id));
                             if let Err(e) = write_file_safely(&file_path, content) {
-                                eprintln!("创建默认便签文件失败 {}: {}", id, e);
+                                error!(note_id = %id, error = %e, "创建默认便签文件失败");
                                 return;
                             }
 
@@ -1986,35 +6172,29 @@ fn main() {
                                 .to_string_lossy()
                                 .to_string();
 
+                            // 初始last_active_at就是创建时间
                             let mut new_entry = NoteEntry {
-                                id: id.clone(),
-                                created_at: created_at.clone(),
-                                last_active_at: created_at.clone(), // 初始last_active_at就是创建时间
                                 expire_at: Some(expires_at.clone()),
-                                cached_preview: None,
-                                status: String::new(), // 禁止手写，将在派生时设置
-                                archived_at: None,
-                                window: Some(WindowInfo {
+                                cached_preview: note_preview("", &startup_settings),
+                                ..NoteEntry::new(id.clone(), created_at.clone(), rel_path, Some(WindowInfo {
                                     x: 100.0,
                                     y: 100.0,
                                     width: 280.0,
                                     height: 360.0,
-                                }),
-                                pinned: false,  // 默认不固定
-                                file: FileInfo {
-                                    relative_path: rel_path,
-                                },
+                                    decorated: false,
+                                    on_all_desktops: false,
+                                    monitor_index: None,
+                                }))
                             };
                             
                             // 派生状态
+                            record_note_event(&mut new_entry, "created", &created_at);
                             derive_status(&mut new_entry);
 
                             index.notes.push(new_entry);
 
-                            let json_content = serde_json::to_string_pretty(&index)
-                                .unwrap_or_else(|_| "{}".to_string());
-                            if let Err(e) = write_file_safely(&index_path, json_content) {
-                                eprintln!("保存默认便签索引失败 {}: {}", id, e);
+                            if let Err(e) = save_index(&app_data_dir, &mut index) {
+                                error!(note_id = %id, error = %e, "保存默认便签索引失败");
                                 return;
                             }
                             
@@ -2031,12 +6211,20 @@ fn main() {
                                 Some(100),
                                 Some(100),
                             ).await {
-                                Ok(_) => println!("创建默认便签窗口: {}", id),
-                                Err(e) => eprintln!("创建默认便签窗口失败 {}: {}", id, e),
+                                Ok(_) => info!(note_id = %id, "创建默认便签窗口"),
+                                Err(e) => error!(note_id = %id, error = %e, "创建默认便签窗口失败"),
                             }
                         }
+
+                        // 所有窗口创建完后再聚焦设置了 focusOnLaunch 的便签，保证它在最前面
+                        if let Some(note_window) = focus_on_launch_id
+                            .and_then(|id| app.app_handle().get_webview_window(&format!("note-{}", id)))
+                        {
+                            let _ = note_window.show();
+                            let _ = note_window.set_focus();
+                        }
                     },
-                    Err(e) => eprintln!("初始化便签目录失败: {}", e),
+                    Err(e) => error!(error = %e, "初始化便签目录失败"),
                 }
             });
 
@@ -2045,3 +6233,171 @@ fn main() {
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_entry(id: &str) -> NoteEntry {
+        NoteEntry {
+            last_active_at: "2024-01-02T09:00:00+08:00".to_string(),
+            expire_at: Some("2024-01-09T09:00:00+08:00".to_string()),
+            cached_preview: Some("预览".to_string()),
+            ..NoteEntry::new(id.to_string(), "2024-01-01T09:00:00+08:00".to_string(), format!("notes/2024/01/01/{}.md", id), None)
+        }
+    }
+
+    fn empty_index() -> IndexFile {
+        IndexFile {
+            version: INDEX_VERSION,
+            app: AppInfo {
+                name: "FadeNote".to_string(),
+                created_at: "2024-01-01T09:00:00+08:00".to_string(),
+                rebuild_at: None,
+            },
+            notes: Vec::new(),
+        }
+    }
+
+    // 每个测试使用独立的临时数据目录，测试结束时删除
+    fn temp_notes_dir() -> PathBuf {
+        let notes_dir = std::env::temp_dir().join(format!("fadenote-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(notes_dir.join("notes")).unwrap();
+        notes_dir
+    }
+
+    fn write_note_file(notes_dir: &Path, id: &str, body: &str) {
+        let dated_dir = notes_dir.join("notes").join("2024").join("01").join("01");
+        fs::create_dir_all(&dated_dir).unwrap();
+        let content = build_full_content(id, "2024-01-01T09:00:00+08:00", body, FrontMatterStyle::FadeNote);
+        fs::write(dated_dir.join(format!("{}.md", id)), content).unwrap();
+    }
+
+    fn local_time(value: &str) -> DateTime<Local> {
+        DateTime::parse_from_rfc3339(value).unwrap().with_timezone(&Local)
+    }
+
+    #[test]
+    fn metadata_setters_leave_expiry_fields_untouched() {
+        // 第二个条目的过期时间早已过去，取消固定 / 永不淡出也不能顺延
+        let mut expired = test_entry("b");
+        expired.expire_at = Some("2000-01-01T09:00:00+08:00".to_string());
+        let colors = NoteColors {
+            bg_color: Some("#ffeeaa".to_string()),
+            text_color: None,
+        };
+
+        for mut entry in [test_entry("a"), expired] {
+            let before = (entry.last_active_at.clone(), entry.expire_at.clone());
+
+            apply_note_pinned(&mut entry, true);
+            apply_note_pinned(&mut entry, false);
+            apply_note_never_expire(&mut entry, true);
+            apply_note_never_expire(&mut entry, false);
+            apply_note_tags(&mut entry, vec!["工作".to_string(), " 工作 ".to_string()]);
+            apply_note_colors(&mut entry, &colors);
+
+            assert_eq!(entry.tags, vec!["工作".to_string()]);
+            assert_eq!((entry.last_active_at.clone(), entry.expire_at.clone()), before);
+        }
+    }
+
+    #[test]
+    fn only_lapsed_temporary_pins_are_reverted() {
+        let now = local_time("2024-02-01T09:00:00+08:00");
+        let mut index = empty_index();
+        for id in ["lapsed", "pending", "manual"] {
+            let mut entry = test_entry(id);
+            entry.pinned = true;
+            entry.expire_at = Some("2000-01-01T09:00:00+08:00".to_string());
+            index.notes.push(entry);
+        }
+        let mut temporary_pins = std::collections::HashMap::from([
+            ("lapsed".to_string(), local_time("2024-02-01T08:59:00+08:00")),
+            ("pending".to_string(), local_time("2024-02-01T09:30:00+08:00")),
+        ]);
+
+        let lapsed_ids = take_temporary_pins(&mut temporary_pins, Some(&now));
+        assert_eq!(unpin_entries(&mut index, &lapsed_ids), vec!["lapsed".to_string()]);
+        let remaining_ids = take_temporary_pins(&mut temporary_pins, None);
+        assert_eq!(unpin_entries(&mut index, &remaining_ids), vec!["pending".to_string()]);
+        assert!(temporary_pins.is_empty());
+        assert_eq!(index.notes.iter().filter(|entry| entry.pinned).count(), 1);
+        // 取消临时固定和 set_note_pinned(false) 一样不顺延过期时间
+        assert!(index.notes.iter().all(|entry| entry.expire_at.as_deref() == Some("2000-01-01T09:00:00+08:00")));
+    }
+
+    #[test]
+    fn file_created_time_falls_back_to_modified_then_now() {
+        let unsupported = || Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "creation time unsupported"));
+        let created = std::time::UNIX_EPOCH + StdDuration::from_secs(1_700_000_000);
+        let modified = std::time::UNIX_EPOCH + StdDuration::from_secs(1_700_000_500);
+        let now = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+
+        assert_eq!(file_created_time(Ok(created), Ok(modified), now), DateTime::<Utc>::from(created));
+        assert_eq!(file_created_time(unsupported(), Ok(modified), now), DateTime::<Utc>::from(modified));
+        assert_eq!(file_created_time(unsupported(), unsupported(), now), now);
+    }
+
+    #[test]
+    fn scanned_entries_have_status() {
+        let notes_dir = temp_notes_dir();
+        write_note_file(&notes_dir, "a", "第一篇");
+        write_note_file(&notes_dir, "b", "第二篇");
+        let notes_path = notes_dir.join("notes");
+
+        let mut scanned = empty_index();
+        scan_directory_for_notes(&notes_dir, &mut scanned, &notes_path, &std::collections::HashSet::new(), &ScheduleSettings::default()).unwrap();
+        let mut rebuilt = empty_index();
+        scan_directory_for_notes_rebuild(&notes_dir, &mut rebuilt, &notes_path, &std::collections::HashMap::new(), &ScheduleSettings::default()).unwrap();
+        fs::remove_dir_all(&notes_dir).unwrap();
+
+        for index in [&scanned, &rebuilt] {
+            assert_eq!(index.notes.len(), 2);
+            assert!(index.notes.iter().all(|entry| !entry.status.is_empty()));
+        }
+    }
+
+    #[test]
+    fn identical_save_skips_file_write() {
+        let created_at = "2024-01-01T09:00:00+08:00";
+        let existing = build_full_content("a", created_at, "正文", FrontMatterStyle::FadeNote);
+
+        assert!(note_file_unchanged(&existing, Some("正文"), "正文", "a", created_at, FrontMatterStyle::FadeNote));
+        assert!(!note_file_unchanged(&existing, Some("正文"), "改过的正文", "a", created_at, FrontMatterStyle::FadeNote));
+        // 切换 Front Matter 风格后需要重写
+        assert!(!note_file_unchanged(&existing, Some("正文"), "正文", "a", created_at, FrontMatterStyle::Obsidian));
+    }
+
+    #[test]
+    fn identical_save_of_encrypted_body_skips_file_write() {
+        let created_at = "2024-01-01T09:00:00+08:00";
+        let salt = new_encryption_salt();
+        let key = derive_body_key("口令", &salt).unwrap();
+        let existing = build_full_content("a", created_at, &encrypt_body(&key, &salt, "正文").unwrap(), FrontMatterStyle::FadeNote);
+        let existing_plain = decrypt_body(&key, &extract_content_only(&existing)).unwrap();
+
+        // 重新加密的密文每次都不同，按解密后的正文比较仍然视为未变化
+        assert_ne!(encrypt_body(&key, &salt, "正文").unwrap(), extract_content_only(&existing));
+        assert!(note_file_unchanged(&existing, Some(existing_plain.as_str()), "正文", "a", created_at, FrontMatterStyle::FadeNote));
+    }
+
+    #[test]
+    fn default_position_stays_within_work_area() {
+        // 主显示器、左侧的副显示器（负坐标）和带任务栏偏移的工作区
+        let work_areas = [(0.0, 0.0, 1280.0, 800.0), (-1920.0, 0.0, 1920.0, 1080.0), (0.0, 40.0, 1366.0, 728.0)];
+        for work_area in work_areas {
+            let (area_x, area_y, area_width, area_height) = work_area;
+            for n in 0..200 {
+                let (x, y) = default_position_in_area(&Uuid::new_v4().to_string(), work_area);
+                assert!(x >= area_x && x + DEFAULT_NOTE_WIDTH <= area_x + area_width, "x={} n={}", x, n);
+                assert!(y >= area_y && y + DEFAULT_NOTE_HEIGHT <= area_y + area_height, "y={} n={}", y, n);
+            }
+        }
+    }
+
+    #[test]
+    fn default_position_in_small_area_is_top_left() {
+        assert_eq!(default_position_in_area("a", (10.0, 20.0, 100.0, 100.0)), (10.0, 20.0));
+    }
+}
@@ -9,12 +9,34 @@ pub struct AppInfo {
     pub rebuild_at: Option<String>,
 }
 
+// 关于页面使用的安装信息：索引中的 AppInfo 加上派生统计
+#[derive(Serialize)]
+pub struct AppInfoSummary {
+    pub name: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "rebuildAt")]
+    pub rebuild_at: Option<String>,
+    #[serde(rename = "installAgeDays")]
+    pub install_age_days: Option<i64>,
+    #[serde(rename = "hasRebuilt")]
+    pub has_rebuilt: bool,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct WindowInfo {
     pub x: f64,
     pub y: f64,
     pub width: f64,
     pub height: f64,
+    #[serde(default)]
+    pub decorated: bool,
+    // 在所有虚拟桌面上显示；仅在支持的平台上生效，见 supports_all_desktops
+    #[serde(rename = "onAllDesktops", default)]
+    pub on_all_desktops: bool,
+    // 绑定的显示器序号；设置后 x / y 为相对该显示器左上角的坐标
+    #[serde(rename = "monitorIndex", default)]
+    pub monitor_index: Option<usize>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -39,9 +61,302 @@ pub struct NoteEntry {
     pub archived_at: Option<String>,
     pub window: Option<WindowInfo>,
     pub pinned: bool,
+    // 永不淡出：与 pinned 一样阻止过期，但不带固定的含义，由前端分别显示
+    #[serde(rename = "neverExpire", default)]
+    pub never_expire: bool,
+    // 过期时间的计算起点：最后活跃时间（默认，编辑会顺延）或创建时间（编辑不顺延）
+    #[serde(rename = "fadeAnchor", default)]
+    pub fade_anchor: FadeAnchor,
+    // 正文在磁盘上的加密方式；是否需要解密只看这个标记，不根据正文内容猜测
+    #[serde(default)]
+    pub encryption: BodyEncryption,
+    // 启动恢复窗口后把这张便签带到前台；同一时间最多一张便签设置
+    #[serde(rename = "focusOnLaunch", default)]
+    pub focus_on_launch: bool,
+    #[serde(rename = "fontSize", default)]
+    pub font_size: Option<u32>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub events: Vec<NoteEvent>,
+    // 提醒时间（RFC3339），到点时重新打开便签窗口
+    #[serde(rename = "reminderAt", default)]
+    pub reminder_at: Option<String>,
+    // 提醒的重复间隔（小时），None 表示只提醒一次
+    #[serde(rename = "reminderRepeatHours", default)]
+    pub reminder_repeat_hours: Option<u32>,
+    // 受保护便签的口令哈希（Argon2 PHC 字符串），None 表示未保护
+    #[serde(default)]
+    pub protected: Option<String>,
+    // 贴边自动隐藏的屏幕边缘：left / right / top / bottom，None 表示不隐藏
+    #[serde(rename = "autoHideEdge", default)]
+    pub auto_hide_edge: Option<String>,
+    // false 时按纯文本编辑和导出，不解释 Markdown 语法
+    #[serde(default = "default_true")]
+    pub markdown: bool,
+    // 便签窗口的背景色 / 文字颜色（#RGB、#RRGGBB 或 #RRGGBBAA），None 表示沿用主题
+    #[serde(rename = "bgColor", default)]
+    pub bg_color: Option<String>,
+    #[serde(rename = "textColor", default)]
+    pub text_color: Option<String>,
     pub file: FileInfo,
 }
 
+impl NoteEntry {
+    // 新条目：lastActiveAt 等于创建时间、没有过期时间，其余字段取默认值，由调用方按需覆盖
+    pub fn new(id: String, created_at: String, relative_path: String, window: Option<WindowInfo>) -> Self {
+        NoteEntry {
+            id,
+            last_active_at: created_at.clone(),
+            created_at,
+            expire_at: None,
+            cached_preview: None,
+            status: String::new(), // 禁止手写，将在派生时设置
+            archived_at: None,
+            window,
+            pinned: false,
+            never_expire: false,
+            fade_anchor: FadeAnchor::default(),
+            encryption: BodyEncryption::None,
+            focus_on_launch: false,
+            font_size: None,
+            tags: Vec::new(),
+            events: Vec::new(),
+            reminder_at: None,
+            reminder_repeat_hours: None,
+            protected: None,
+            auto_hide_edge: None,
+            markdown: true,
+            bg_color: None,
+            text_color: None,
+            file: FileInfo { relative_path },
+        }
+    }
+}
+
+// 便签生命周期事件：created / edited / archived / restored
+#[derive(Serialize, Deserialize, Clone)]
+pub struct NoteEvent {
+    pub kind: String,
+    pub at: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct OrphanFile {
+    pub id: String,
+    #[serde(rename = "relativePath")]
+    pub relative_path: String,
+    pub preview: Option<String>,
+}
+
+// 推送给便签窗口的颜色设置，None 表示沿用主题
+#[derive(Serialize, Clone)]
+pub struct NoteColors {
+    #[serde(rename = "bgColor")]
+    pub bg_color: Option<String>,
+    #[serde(rename = "textColor")]
+    pub text_color: Option<String>,
+}
+
+// 最近一条错误日志：发生时间（RFC3339）和消息
+#[derive(Serialize, Clone)]
+pub struct RecentError {
+    pub at: String,
+    pub message: String,
+}
+
+// 索引变更事件的轻量摘要
+#[derive(Serialize, Clone)]
+pub struct IndexChange {
+    pub kind: String,
+    pub ids: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct QuickSwitchItem {
+    pub id: String,
+    pub preview: Option<String>,
+}
+
+// 活跃便签当前的窗口状态：窗口是否已创建、是否可见
+#[derive(Serialize)]
+pub struct NoteWindowState {
+    pub id: String,
+    #[serde(rename = "windowExists")]
+    pub window_exists: bool,
+    pub visible: bool,
+}
+
+// 活跃便签的剩余寿命，secondsRemaining 为 None 表示永不淡出（固定或没有过期时间）
+#[derive(Serialize)]
+pub struct NoteUrgency {
+    pub id: String,
+    pub preview: Option<String>,
+    #[serde(rename = "expireAt")]
+    pub expire_at: Option<String>,
+    pub pinned: bool,
+    #[serde(rename = "secondsRemaining")]
+    pub seconds_remaining: Option<i64>,
+}
+
+// 归档表格的一行：显示字段由后端统一计算，文件缺失时 missing 为 true、wordCount 为 0
+#[derive(Serialize)]
+pub struct ArchiveRow {
+    pub id: String,
+    #[serde(rename = "titleOrPreview")]
+    pub title_or_preview: Option<String>,
+    #[serde(rename = "createdDate")]
+    pub created_date: String,
+    #[serde(rename = "archivedDate")]
+    pub archived_date: Option<String>,
+    #[serde(rename = "wordCount")]
+    pub word_count: usize,
+    pub pinned: bool,
+    pub missing: bool,
+}
+
+// notes/ 下一个日期目录的便签数；folder 为空表示直接放在 notes/ 下的文件（flat 粒度）
+#[derive(Serialize)]
+pub struct FolderSummary {
+    pub folder: String,
+    #[serde(rename = "mdCount")]
+    pub md_count: usize,
+    #[serde(rename = "activeCount")]
+    pub active_count: usize,
+}
+
+// 按索引中 relativePath 的日期目录分组的便签数；路径不在日期目录下的归入 "unknown"
+#[derive(Serialize)]
+pub struct DateBucket {
+    pub date: String,
+    pub active: u32,
+    pub archived: u32,
+}
+
+#[derive(Serialize)]
+pub struct StorageUsage {
+    #[serde(rename = "totalBytes")]
+    pub total_bytes: u64,
+    #[serde(rename = "noteCount")]
+    pub note_count: usize,
+    #[serde(rename = "archivedBytes")]
+    pub archived_bytes: u64,
+    #[serde(rename = "attachmentBytes")]
+    pub attachment_bytes: u64,
+}
+
+// 超过 maxNoteBytes 的便签只加载前一部分，truncated 标记为 true
+// 受保护且未解锁的便签不返回正文，locked 标记为 true
+#[derive(Serialize)]
+pub struct LoadedNote {
+    pub content: String,
+    pub truncated: bool,
+    pub locked: bool,
+}
+
+// 启动时索引检查的摘要：是否重建、新收录的孤立文件、文件缺失的条目
+#[derive(Serialize, Clone, Default)]
+pub struct StartupReport {
+    pub rebuilt: bool,
+    pub adopted: Vec<String>,
+    #[serde(rename = "missingFiles")]
+    pub missing_files: Vec<String>,
+}
+
+impl StartupReport {
+    pub fn is_notable(&self) -> bool {
+        self.rebuilt || !self.adopted.is_empty() || !self.missing_files.is_empty()
+    }
+}
+
+#[derive(Serialize)]
+pub struct NoteTags {
+    pub id: String,
+    pub tags: Vec<String>,
+}
+
+// 批量修改标签的结果：每个便签修改后的标签，以及索引中找不到的ID
+#[derive(Serialize)]
+pub struct BulkTagsReport {
+    pub updated: Vec<NoteTags>,
+    #[serde(rename = "notFound")]
+    pub not_found: Vec<String>,
+}
+
+// 导入外部文件的结果：新建的便签ID、因ID冲突换用新ID的文件数、因内容重复跳过的文件数，以及读取、写入失败或过大的文件路径
+#[derive(Serialize)]
+pub struct ImportReport {
+    pub imported: Vec<String>,
+    pub remapped: usize,
+    #[serde(rename = "skippedDuplicates")]
+    pub skipped_duplicates: usize,
+    pub failed: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct RepairPathsReport {
+    pub relinked: Vec<String>,
+    pub unresolved: Vec<String>,
+}
+
+// check_index 能发现的问题类型
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum IndexProblem {
+    EmptyId,
+    DuplicateId,
+    EmptyCreatedAt,
+    EmptyLastActiveAt,
+    MissingExpireAt,
+    UnparseableTimestamp,
+    WindowTooSmall,
+    EmptyFilePath,
+    StatusMismatch,
+    MissingFile,
+    OrphanFile,
+}
+
+// check_index 发现的问题：kind 为问题类型，id 为相关便签ID（ID 为空或孤立文件没有 id 时为空）
+#[derive(Serialize)]
+pub struct IndexIssue {
+    pub id: String,
+    pub kind: IndexProblem,
+    pub detail: String,
+}
+
+// export_clean 写在导出目录根部的 manifest.json
+#[derive(Serialize)]
+pub struct CleanExportManifest {
+    #[serde(rename = "exportedAt")]
+    pub exported_at: String,
+    pub notes: Vec<CleanExportEntry>,
+}
+
+#[derive(Serialize)]
+pub struct CleanExportEntry {
+    pub id: String,
+    #[serde(rename = "relativePath")]
+    pub relative_path: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    pub archived: bool,
+    pub tags: Vec<String>,
+}
+
+// 保存在 layouts/{name}.json 中的桌面布局：各活跃便签当时的窗口信息
+#[derive(Serialize, Deserialize)]
+pub struct NoteLayout {
+    #[serde(rename = "savedAt")]
+    pub saved_at: String,
+    pub windows: Vec<LayoutWindow>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LayoutWindow {
+    pub id: String,
+    pub window: WindowInfo,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct IndexFile {
     pub version: u32,
@@ -61,6 +376,103 @@ pub struct ScheduleSettings {
     pub language: String,
     #[serde(rename = "lastTriggeredKey")]
     pub last_triggered_key: Option<String>,
+    #[serde(rename = "defaultNoteWidth", default)]
+    pub default_note_width: Option<f64>,
+    #[serde(rename = "defaultNoteHeight", default)]
+    pub default_note_height: Option<f64>,
+    #[serde(rename = "defaultNoteX", default)]
+    pub default_note_x: Option<f64>,
+    #[serde(rename = "defaultNoteY", default)]
+    pub default_note_y: Option<f64>,
+    #[serde(default)]
+    pub cascade: bool,
+    #[serde(rename = "maxActiveNotes", default)]
+    pub max_active_notes: Option<usize>,
+    #[serde(rename = "frontMatter", default = "default_front_matter")]
+    pub front_matter: String,
+    #[serde(rename = "previewChars", default = "default_preview_chars")]
+    pub preview_chars: usize,
+    #[serde(rename = "previewLines", default = "default_preview_lines")]
+    pub preview_lines: usize,
+    #[serde(rename = "purgeArchivedAfterDays", default)]
+    pub purge_archived_after_days: Option<u32>,
+    #[serde(rename = "discardEmptyOnExpire", default)]
+    pub discard_empty_on_expire: bool,
+    #[serde(rename = "startupBehavior", default)]
+    pub startup_behavior: StartupBehavior,
+    #[serde(rename = "restoreWindowsOnStartup", default = "default_true")]
+    pub restore_windows_on_startup: bool,
+    #[serde(rename = "logLevel", default = "default_log_level")]
+    pub log_level: String,
+    #[serde(rename = "maxNoteBytes", default = "default_max_note_bytes")]
+    pub max_note_bytes: u64,
+    #[serde(rename = "extendOnRead", default)]
+    pub extend_on_read: bool,
+    // 首次启动的欢迎便签从内置提示和 tips.txt 中随机挑选一条
+    #[serde(rename = "rotateWelcome", default)]
+    pub rotate_welcome: bool,
+    // 冻结淡出：frozenUntil 之前不归档任何便签，frozenAt 记录冻结开始的时间，用于解冻时补回过期时间
+    #[serde(rename = "frozenUntil", default)]
+    pub frozen_until: Option<String>,
+    #[serde(rename = "frozenAt", default)]
+    pub frozen_at: Option<String>,
+    #[serde(rename = "folderGranularity", default)]
+    pub folder_granularity: FolderGranularity,
+    // 关闭便签窗口即归档（固定便签仍只隐藏）
+    #[serde(rename = "archiveOnClose", default)]
+    pub archive_on_close: bool,
+    // 非首次启动且没有恢复任何窗口时是否新建空白便签；关闭后只保留托盘图标，不影响首次启动的欢迎便签
+    #[serde(rename = "createBlankNoteWhenEmpty", default = "default_true")]
+    pub create_blank_note_when_empty: bool,
+    // 登录系统时自动启动；默认关闭，由前端在首次启动时询问
+    #[serde(default)]
+    pub autostart: bool,
+    // 运行期间不淡出：只归档启动前就已过期的便签
+    #[serde(rename = "pauseExpiryWhileRunning", default)]
+    pub pause_expiry_while_running: bool,
+    // 全局正文加密：派生密钥用的盐（十六进制）和口令校验串，None 表示未开启；只由 set_encryption_passphrase 修改
+    #[serde(rename = "encryptionSalt", default)]
+    pub encryption_salt: Option<String>,
+    #[serde(rename = "encryptionVerifier", default)]
+    pub encryption_verifier: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum FadeAnchor {
+    #[default]
+    LastActive,
+    Created,
+}
+
+// 正文加密方式：明文、单个便签口令加密（需先 unlock_note）、全局口令加密（见 set_encryption_passphrase）
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum BodyEncryption {
+    #[default]
+    None,
+    Note,
+    Global,
+}
+
+// 新便签文件的目录粒度：notes/YYYY-MM-DD（默认）、notes/YYYY-MM、直接放在 notes/ 下
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum FolderGranularity {
+    #[default]
+    Daily,
+    Monthly,
+    Flat,
+}
+
+// 启动时的窗口行为：恢复后无便签则新建（默认）、只恢复、恢复后总是新建
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum StartupBehavior {
+    #[default]
+    RestoreThenNewIfEmpty,
+    RestoreOnly,
+    AlwaysNew,
 }
 
 fn default_theme() -> String {
@@ -71,6 +483,30 @@ fn default_language() -> String {
     "system".to_string()
 }
 
+fn default_front_matter() -> String {
+    "fadenote".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_note_bytes() -> u64 {
+    1024 * 1024
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_preview_chars() -> usize {
+    50
+}
+
+fn default_preview_lines() -> usize {
+    1
+}
+
 impl Default for ScheduleSettings {
     fn default() -> Self {
         Self {
@@ -81,6 +517,32 @@ impl Default for ScheduleSettings {
             theme: default_theme(),
             language: default_language(),
             last_triggered_key: None,
+            default_note_width: None,
+            default_note_height: None,
+            default_note_x: None,
+            default_note_y: None,
+            cascade: false,
+            max_active_notes: None,
+            front_matter: default_front_matter(),
+            preview_chars: default_preview_chars(),
+            preview_lines: default_preview_lines(),
+            purge_archived_after_days: None,
+            discard_empty_on_expire: false,
+            startup_behavior: StartupBehavior::default(),
+            restore_windows_on_startup: true,
+            log_level: default_log_level(),
+            max_note_bytes: default_max_note_bytes(),
+            extend_on_read: false,
+            rotate_welcome: false,
+            frozen_until: None,
+            frozen_at: None,
+            folder_granularity: FolderGranularity::default(),
+            archive_on_close: false,
+            create_blank_note_when_empty: true,
+            autostart: false,
+            pause_expiry_while_running: false,
+            encryption_salt: None,
+            encryption_verifier: None,
         }
     }
 }
@@ -1,4 +1,39 @@
+// Front Matter 的键名风格：FadeNote 原生（id / createdAt）或 Obsidian 兼容（fadenote-id / created）
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FrontMatterStyle {
+    FadeNote,
+    Obsidian,
+}
+
+impl FrontMatterStyle {
+    pub fn from_setting(value: &str) -> Self {
+        match value {
+            "obsidian" => FrontMatterStyle::Obsidian,
+            _ => FrontMatterStyle::FadeNote,
+        }
+    }
+}
+
+const ID_KEYS: [&str; 2] = ["id:", "fadenote-id:"];
+const CREATED_AT_KEYS: [&str; 2] = ["createdAt:", "created:"];
+
+// 去掉部分 Windows 编辑器写入的 UTF-8 BOM，并把 CRLF / 单独的 CR 统一为 LF
+fn normalize_line_endings(content: &str) -> String {
+    content
+        .strip_prefix('\u{feff}')
+        .unwrap_or(content)
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+}
+
+fn front_matter_value(line: &str, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .find(|key| line.starts_with(*key))
+        .map(|key| line[key.len()..].trim().to_string())
+}
+
 pub fn parse_id_from_content(content: &str) -> Option<String> {
+    let content = normalize_line_endings(content);
     let lines: Vec<&str> = content.lines().collect();
     let mut in_front_matter = false;
 
@@ -9,10 +44,9 @@ pub fn parse_id_from_content(content: &str) -> Option<String> {
             } else {
                 break;
             }
-        } else if in_front_matter && line.starts_with("id:") {
-            let parts: Vec<&str> = line.splitn(2, ':').collect();
-            if parts.len() == 2 {
-                return Some(parts[1].trim().to_string());
+        } else if in_front_matter {
+            if let Some(value) = front_matter_value(line, &ID_KEYS) {
+                return Some(value);
             }
         }
     }
@@ -21,6 +55,7 @@ pub fn parse_id_from_content(content: &str) -> Option<String> {
 }
 
 pub fn extract_content_only(content: &str) -> String {
+    let content = normalize_line_endings(content);
     let lines: Vec<&str> = content.lines().collect();
     let mut content_start = 0;
 
@@ -35,9 +70,9 @@ pub fn extract_content_only(content: &str) -> String {
 
                 for line in lines.iter().take(actual_end_idx).skip(actual_start_idx + 1) {
                     let line = line.trim();
-                    if line.starts_with("id:") {
+                    if front_matter_value(line, &ID_KEYS).is_some() {
                         found_id = true;
-                    } else if line.starts_with("createdAt:") {
+                    } else if front_matter_value(line, &CREATED_AT_KEYS).is_some() {
                         found_created_at = true;
                     }
                 }
@@ -63,22 +98,73 @@ pub fn extract_content_only(content: &str) -> String {
     }
 }
 
-pub fn build_full_content(id: &str, created_at: &str, content: &str) -> String {
-    format!("---\nid: {}\ncreatedAt: {}\n---\n{}", id, created_at, content)
+// 去掉开头的 Front Matter 块（不要求 id / createdAt 齐全），用于修复残缺的 Front Matter
+pub fn strip_leading_front_matter(content: &str) -> String {
+    let content = normalize_line_endings(content);
+    let lines: Vec<&str> = content.lines().collect();
+    if lines.first().map(|line| line.trim()) != Some("---") {
+        return content;
+    }
+    let end_idx = match lines[1..].iter().position(|line| line.trim() == "---") {
+        Some(idx) => idx + 1,
+        None => return content,
+    };
+    if !lines[1..end_idx].iter().all(|line| line.trim().is_empty() || line.contains(':')) {
+        return content;
+    }
+
+    let body_start = if lines.get(end_idx + 1).is_some_and(|line| line.is_empty()) {
+        end_idx + 2
+    } else {
+        end_idx + 1
+    };
+    lines.get(body_start..).map(|rest| rest.join("\n")).unwrap_or_default()
 }
 
-pub fn extract_first_line_preview(content: &str) -> Option<String> {
-    for line in content.lines() {
-        let trimmed = line.trim();
-        if !trimmed.is_empty() {
-            return Some(trimmed.chars().take(50).collect());
-        }
+// 正文的内容哈希：逐行去掉行尾空白、整体去掉首尾空行后计算，用于判断两篇便签内容是否相同
+pub fn content_hash(body: &str) -> String {
+    let normalized = normalize_line_endings(body);
+    let normalized: Vec<&str> = normalized.lines().map(|line| line.trim_end()).collect();
+    body_sha256(normalized.join("\n").trim())
+}
+
+// 正文的 SHA-256（十六进制），先统一换行符，保证不同平台上结果一致
+pub fn body_sha256(body: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    Sha256::digest(normalize_line_endings(body).as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// 写入的文件统一使用 LF，正文中的 CRLF 不会和 Front Matter 的换行混在一起
+pub fn build_full_content(id: &str, created_at: &str, content: &str, style: FrontMatterStyle) -> String {
+    let content = normalize_line_endings(content);
+    match style {
+        FrontMatterStyle::FadeNote => format!("---\nid: {}\ncreatedAt: {}\n---\n{}", id, created_at, content),
+        // 标准 YAML 写法，正文前留一个空行
+        FrontMatterStyle::Obsidian => format!("---\nfadenote-id: {}\ncreated: {}\n---\n\n{}", id, created_at, content),
     }
+}
 
-    None
+// 取前 max_lines 个非空行（以空格连接），截断为 max_chars 个字符
+pub fn extract_preview(content: &str, max_chars: usize, max_lines: usize) -> Option<String> {
+    let lines: Vec<&str> = content
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .take(max_lines.max(1))
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+
+    Some(lines.join(" ").chars().take(max_chars).collect())
 }
 
 pub fn extract_created_at_from_content(content: &str) -> Option<String> {
+    let content = normalize_line_endings(content);
     let lines: Vec<&str> = content.lines().collect();
     let mut in_front_matter = false;
 
@@ -89,13 +175,229 @@ pub fn extract_created_at_from_content(content: &str) -> Option<String> {
             } else {
                 break;
             }
-        } else if in_front_matter && line.starts_with("createdAt:") {
-            let parts: Vec<&str> = line.splitn(2, ':').collect();
-            if parts.len() == 2 {
-                return Some(parts[1].trim().to_string());
+        } else if in_front_matter {
+            if let Some(value) = front_matter_value(line, &CREATED_AT_KEYS) {
+                return Some(value);
             }
         }
     }
 
     None
 }
+
+// 字数：以空白分隔的词计数，中日韩文字每个字单独计为一个词
+pub fn word_count(body: &str) -> usize {
+    let mut count = 0;
+    let mut in_word = false;
+    for c in body.chars() {
+        if c.is_whitespace() {
+            in_word = false;
+        } else if is_cjk(c) {
+            count += 1;
+            in_word = false;
+        } else if !in_word {
+            count += 1;
+            in_word = true;
+        }
+    }
+    count
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30ff // 平假名、片假名
+        | 0x3400..=0x4dbf // 扩展 A
+        | 0x4e00..=0x9fff // 基本汉字
+        | 0xac00..=0xd7af // 韩文音节
+        | 0xf900..=0xfaff) // 兼容汉字
+}
+
+// 把 Markdown 正文转成适合朗读的纯文本：去掉标题/引用/列表标记、强调和行内代码符号，
+// 链接和图片只保留文字，代码块围栏行删除；每行内的连续空白合并为一个空格，连续空行只保留一个
+pub fn markdown_to_plaintext(body: &str) -> String {
+    let body = normalize_line_endings(body);
+    let mut lines: Vec<String> = Vec::new();
+    for line in body.lines() {
+        let mut line = line.trim();
+        if line.starts_with("```") || line.starts_with("~~~") {
+            continue;
+        }
+        line = line.trim_start_matches('#').trim_start();
+        line = line.trim_start_matches('>').trim_start();
+        for marker in ["- [ ] ", "- [x] ", "- ", "* ", "+ "] {
+            if let Some(rest) = line.strip_prefix(marker) {
+                line = rest;
+                break;
+            }
+        }
+
+        let text = strip_inline_markdown(line);
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(text);
+    }
+    while lines.last().is_some_and(|last| last.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+// 去掉 * ` ~ 强调符号，[文字](链接) 与 ![文字](链接) 只保留文字
+fn strip_inline_markdown(line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut text = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' | '`' | '~' => i += 1,
+            '!' if chars.get(i + 1) == Some(&'[') => i += 1,
+            '[' => {
+                let close = chars[i + 1..].iter().position(|c| *c == ']').map(|pos| i + 1 + pos);
+                match close {
+                    Some(close) if chars.get(close + 1) == Some(&'(') => {
+                        text.push_str(&strip_inline_markdown(&chars[i + 1..close].iter().collect::<String>()));
+                        i = chars[close + 1..].iter()
+                            .position(|c| *c == ')')
+                            .map_or(chars.len(), |pos| close + 1 + pos + 1);
+                    }
+                    _ => {
+                        text.push('[');
+                        i += 1;
+                    }
+                }
+            }
+            c => {
+                text.push(c);
+                i += 1;
+            }
+        }
+    }
+    text
+}
+
+// 把 Markdown 正文渲染成只读预览用的 HTML：原始 HTML 一律按文本转义输出，
+// 链接 / 图片只保留 http(s)、mailto 和相对地址，其余协议（如 javascript:）的地址清空
+pub fn render_markdown_html(body: &str) -> String {
+    use pulldown_cmark::{html, Event, Options, Parser, Tag};
+
+    let body = normalize_line_endings(body);
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let events = Parser::new_ext(&body, options).map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        Event::Start(Tag::Link { link_type, dest_url, title, id }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image { link_type, dest_url, title, id }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        event => event,
+    });
+
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, events);
+    rendered
+}
+
+fn safe_url(url: pulldown_cmark::CowStr<'_>) -> pulldown_cmark::CowStr<'_> {
+    if is_safe_url(&url) {
+        url
+    } else {
+        pulldown_cmark::CowStr::Borrowed("")
+    }
+}
+
+// 关闭 Markdown 的便签按纯文本预览：转义后放进 <pre>
+pub fn render_plaintext_html(body: &str) -> String {
+    format!("<pre>{}</pre>", escape_html(&normalize_line_endings(body)))
+}
+
+fn is_safe_url(url: &str) -> bool {
+    let lower = url.trim().to_ascii_lowercase();
+    match lower.find(':') {
+        // 冒号出现在 / ? # 之前才算协议，否则是相对地址
+        Some(colon) if !lower[..colon].contains(['/', '?', '#']) => {
+            ["http", "https", "mailto"].contains(&&lower[..colon])
+        }
+        _ => true,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOM_NOTE: &str = "\u{feff}---\nid: note-1\ncreatedAt: 2024-01-01T09:00:00+08:00\n---\n第一行\n第二行";
+    const BOM_CRLF_NOTE: &str = "\u{feff}---\r\nid: note-1\r\ncreatedAt: 2024-01-01T09:00:00+08:00\r\n---\r\n第一行\r\n第二行";
+
+    #[test]
+    fn parses_front_matter_after_bom() {
+        for content in [BOM_NOTE, BOM_CRLF_NOTE] {
+            assert_eq!(parse_id_from_content(content).as_deref(), Some("note-1"));
+            assert_eq!(extract_created_at_from_content(content).as_deref(), Some("2024-01-01T09:00:00+08:00"));
+            assert_eq!(extract_content_only(content), "第一行\n第二行");
+        }
+    }
+
+    #[test]
+    fn parses_obsidian_front_matter_after_bom() {
+        let content = "\u{feff}---\r\nfadenote-id: note-2\r\ncreated: 2024-01-01T09:00:00+08:00\r\n---\r\n\r\n正文";
+        assert_eq!(parse_id_from_content(content).as_deref(), Some("note-2"));
+        assert_eq!(extract_created_at_from_content(content).as_deref(), Some("2024-01-01T09:00:00+08:00"));
+        assert_eq!(extract_content_only(content), "正文");
+    }
+
+    #[test]
+    fn strips_incomplete_front_matter_after_bom() {
+        let content = "\u{feff}---\r\ntitle: 残缺\r\n---\r\n正文";
+        assert_eq!(strip_leading_front_matter(content), "正文");
+    }
+
+    const CRLF_NOTE: &str = "---\r\nid: note-3\r\ncreatedAt: 2024-01-01T09:00:00+08:00\r\n---\r\n第一行\r\n\r\n第三行\r\n";
+
+    #[test]
+    fn parse_id_from_crlf_content() {
+        assert_eq!(parse_id_from_content(CRLF_NOTE).as_deref(), Some("note-3"));
+    }
+
+    #[test]
+    fn extract_created_at_from_crlf_content() {
+        assert_eq!(extract_created_at_from_content(CRLF_NOTE).as_deref(), Some("2024-01-01T09:00:00+08:00"));
+    }
+
+    #[test]
+    fn extract_content_only_from_crlf_content() {
+        let body = extract_content_only(CRLF_NOTE);
+        assert_eq!(body, "第一行\n\n第三行");
+        assert!(!body.contains('\r'));
+    }
+
+    #[test]
+    fn crlf_body_is_written_with_lf() {
+        let content = build_full_content("note-3", "2024-01-01T09:00:00+08:00", "第一行\r\n第二行", FrontMatterStyle::FadeNote);
+        assert_eq!(content, "---\nid: note-3\ncreatedAt: 2024-01-01T09:00:00+08:00\n---\n第一行\n第二行");
+        assert_eq!(body_sha256("第一行\r\n第二行"), body_sha256("第一行\n第二行"));
+    }
+}
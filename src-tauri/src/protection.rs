@@ -0,0 +1,110 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+// 用 Argon2 和随机盐生成口令哈希（PHC 字符串，包含算法参数和盐），索引中只保存这个哈希
+pub fn hash_passphrase(passphrase: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(passphrase.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("生成口令哈希失败: {}", e))
+}
+
+// 校验口令；哈希无法解析时视为不匹配
+pub fn verify_passphrase(passphrase: &str, stored_hash: &str) -> bool {
+    match PasswordHash::new(stored_hash) {
+        Ok(hash) => Argon2::default().verify_password(passphrase.as_bytes(), &hash).is_ok(),
+        Err(_) => false,
+    }
+}
+
+// 加密正文的格式：前缀 + 盐 + nonce + 密文（均为十六进制），Front Matter 保持明文
+const ENCRYPTED_PREFIX: &str = "fadenote-encrypted:v1:";
+
+pub fn new_encryption_salt() -> Vec<u8> {
+    let mut salt = vec![0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+// 用 Argon2 从口令和盐派生 32 字节的正文加密密钥
+pub fn derive_body_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("派生加密密钥失败: {}", e))?;
+    Ok(key)
+}
+
+// 读取加密正文中的盐，用于解锁时重新派生密钥
+pub fn encrypted_body_salt(body: &str) -> Option<Vec<u8>> {
+    let rest = body.trim().strip_prefix(ENCRYPTED_PREFIX)?;
+    from_hex(rest.split(':').next()?)
+}
+
+pub fn encrypt_body(key: &[u8; 32], salt: &[u8], plaintext: &str) -> Result<String, String> {
+    let mut nonce = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce);
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+        .map_err(|_| "加密便签内容失败".to_string())?;
+    Ok(format!("{}{}:{}:{}", ENCRYPTED_PREFIX, to_hex(salt), to_hex(&nonce), to_hex(&ciphertext)))
+}
+
+// 密钥错误或密文被篡改时返回错误，不会产生部分解密的内容
+pub fn decrypt_body(key: &[u8; 32], body: &str) -> Result<String, String> {
+    let parts: Vec<&str> = body.trim()
+        .strip_prefix(ENCRYPTED_PREFIX)
+        .ok_or_else(|| "便签内容未加密".to_string())?
+        .split(':')
+        .collect();
+    let (nonce, ciphertext) = match parts.as_slice() {
+        [_, nonce, ciphertext] => (from_hex(nonce), from_hex(ciphertext)),
+        _ => (None, None),
+    };
+    let (nonce, ciphertext) = match (nonce, ciphertext) {
+        (Some(nonce), Some(ciphertext)) if nonce.len() == 12 => (nonce, ciphertext),
+        _ => return Err("加密内容格式无效".to_string()),
+    };
+    let plaintext = ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| "解密便签内容失败".to_string())?;
+    String::from_utf8(plaintext).map_err(|_| "解密后的内容不是有效的 UTF-8".to_string())
+}
+
+// 全局正文加密的口令校验串：用派生密钥加密一段固定明文，能解密出同样的明文即口令正确
+const VERIFIER_PLAINTEXT: &str = "fadenote-passphrase-verifier";
+
+pub fn new_passphrase_verifier(key: &[u8; 32], salt: &[u8]) -> Result<String, String> {
+    encrypt_body(key, salt, VERIFIER_PLAINTEXT)
+}
+
+pub fn check_passphrase_verifier(key: &[u8; 32], verifier: &str) -> bool {
+    decrypt_body(key, verifier).is_ok_and(|plaintext| plaintext == VERIFIER_PLAINTEXT)
+}
+
+// settings.json 中的盐以十六进制保存
+pub fn salt_to_hex(salt: &[u8]) -> String {
+    to_hex(salt)
+}
+
+pub fn salt_from_hex(value: &str) -> Option<Vec<u8>> {
+    from_hex(value)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
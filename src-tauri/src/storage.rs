@@ -1,14 +1,141 @@
 use std::fs;
 use std::io::Write;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 use dirs::data_dir;
 use uuid::Uuid;
 
+// 系统的 AppData 目录；无头或受限环境下可能拿不到
+pub fn system_app_data_dir() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("FadeNote"))
+}
+
+// 用户自选数据目录的指针文件，固定放在系统 AppData 目录下
+// （settings.json 本身位于数据目录中，无法用来记录数据目录的位置）
+fn data_directory_pointer_path() -> Option<PathBuf> {
+    system_app_data_dir().map(|dir| dir.join("data-directory.txt"))
+}
+
+// 读取用户自选的数据目录；指针文件缺失或目录已不存在时返回 None
+fn custom_data_dir() -> Option<PathBuf> {
+    let content = fs::read_to_string(data_directory_pointer_path()?).ok()?;
+    let dir = PathBuf::from(content.trim());
+    if dir.is_dir() {
+        Some(dir)
+    } else {
+        None
+    }
+}
+
+pub fn set_custom_data_dir(dir: &Path) -> Result<(), String> {
+    let pointer_path = data_directory_pointer_path()
+        .ok_or_else(|| "system data directory unavailable".to_string())?;
+    write_file_safely(pointer_path, dir.to_string_lossy().as_bytes())
+}
+
+// 优先使用用户自选的数据目录；拿不到系统 AppData 目录时回退到临时目录，避免启动时直接崩溃
 pub fn get_app_data_dir() -> Result<PathBuf, String> {
-    let mut app_data_dir = data_dir().ok_or("无法获取AppData目录")?;
-    app_data_dir.push("FadeNote");
-    Ok(app_data_dir)
+    if let Some(dir) = custom_data_dir() {
+        return Ok(dir);
+    }
+    Ok(system_app_data_dir().unwrap_or_else(|| std::env::temp_dir().join("FadeNote")))
+}
+
+// 把相对路径安全地拼接到数据目录下，拒绝绝对路径和 `..` 等越界写法
+pub fn resolve_relative_path(base: &Path, relative_path: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(relative_path);
+    if relative_path.trim().is_empty()
+        || !relative.components().all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(format!("invalid relative path: {}", relative_path));
+    }
+    Ok(base.join(relative))
+}
+
+// 把用户输入的 slug 清理成可用的文件名：保留字母数字、`-`、`_`，空白转成 `-`
+pub fn sanitize_slug(slug: &str) -> Option<String> {
+    let mut sanitized = String::new();
+    for c in slug.trim().chars() {
+        if c.is_alphanumeric() || c == '-' || c == '_' {
+            sanitized.push(c);
+        } else if c.is_whitespace() && !sanitized.ends_with('-') {
+            sanitized.push('-');
+        }
+    }
+    let sanitized = sanitized.trim_matches('-').chars().take(80).collect::<String>();
+    if sanitized.is_empty() {
+        None
+    } else {
+        Some(sanitized)
+    }
+}
+
+// 在目录中找一个不冲突的 `{stem}.md` 文件名，冲突时追加数字后缀
+pub fn unique_markdown_path(dir: &Path, stem: &str, current: &Path) -> PathBuf {
+    let mut candidate = dir.join(format!("{}.md", stem));
+    let mut suffix = 2;
+    while candidate.exists() && candidate != current {
+        candidate = dir.join(format!("{}-{}.md", stem, suffix));
+        suffix += 1;
+    }
+    candidate
+}
+
+// 递归收集目录下所有 .md 文件；遍历中出错的条目直接跳过
+pub fn collect_markdown_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return files,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(collect_markdown_files(&path));
+        } else if path.is_file() && path.extension().is_some_and(|ext| ext == "md") {
+            files.push(path);
+        }
+    }
+    files
+}
+
+// 递归收集目录下所有文件及其大小；遍历中消失或无法读取的文件直接跳过
+pub fn collect_file_sizes(dir: &Path) -> Vec<(PathBuf, u64)> {
+    let mut files = Vec::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return files,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if metadata.is_dir() {
+            files.extend(collect_file_sizes(&path));
+        } else if metadata.is_file() {
+            files.push((path, metadata.len()));
+        }
+    }
+    files
+}
+
+// 递归复制目录，已存在的同名文件不覆盖
+pub fn copy_dir_recursive(from: &Path, to: &Path) -> Result<(), String> {
+    fs::create_dir_all(to).map_err(|e| format!("create directory failed {}: {}", to.display(), e))?;
+    let entries = fs::read_dir(from).map_err(|e| format!("read directory failed {}: {}", from.display(), e))?;
+    for entry in entries.flatten() {
+        let source = entry.path();
+        let target = to.join(entry.file_name());
+        if source.is_dir() {
+            copy_dir_recursive(&source, &target)?;
+        } else if source.is_file() && !target.exists() {
+            fs::copy(&source, &target)
+                .map_err(|e| format!("copy file failed {}: {}", source.display(), e))?;
+        }
+    }
+    Ok(())
 }
 
 pub fn write_file_safely(path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> Result<(), String> {
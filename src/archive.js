@@ -77,10 +77,13 @@ async function showArchiveConfirm(title, message) {
 }
 
 
-// 加载归档便签列表
-async function loadArchivedNotes() {
+// 加载归档便签列表；readOnly 为 true 时只读索引（响应其他窗口的变化），不触发索引修复
+async function loadArchivedNotes(readOnly = false) {
   try {
-    const archivedNotes = await window.__TAURI__.core.invoke('get_archived_notes');
+    const archivedNotes = readOnly
+      ? (await window.__TAURI__.core.invoke('get_archived_previews'))
+        .map(([id, preview, archivedAt]) => ({ id, cachedPreview: preview, archivedAt }))
+      : await window.__TAURI__.core.invoke('get_archived_notes');
     const archiveList = document.getElementById('archive-list');
     
     if (archivedNotes.length === 0) {
@@ -119,23 +122,8 @@ async function loadArchivedNotes() {
         try {
           await window.__TAURI__.core.invoke('restore_note', { id: note.id });
           
-          // 创建便签窗口
-          const windowInfo = note.window || {
-            x: 200,
-            y: 200,
-            width: 280,
-            height: 360
-          };
-          
-          const label = `note-${note.id}`;
-          await window.__TAURI__.core.invoke('create_note_window', {
-            label: label,
-            title: "FadeNote",
-            width: Math.round(windowInfo.width),
-            height: Math.round(windowInfo.height),
-            x: Math.round(windowInfo.x),
-            y: Math.round(windowInfo.y)
-          });
+          // 按索引中保存的位置创建便签窗口
+          await window.__TAURI__.core.invoke('focus_note', { id: note.id });
           
           // 重新加载列表
           loadArchivedNotes();
@@ -192,4 +180,11 @@ document.addEventListener('DOMContentLoaded', () => {
   window.__TAURI__?.event?.listen('fadenote://language-changed', () => {
     setTimeout(loadArchivedNotes, 0);
   });
+  // 其他窗口归档/恢复/删除便签后实时刷新列表；普通的编辑和窗口移动不影响归档列表
+  const refreshKinds = ['archived', 'restored', 'deleted', 'created'];
+  window.__TAURI__?.event?.listen('fadenote://index-changed', (event) => {
+    if (refreshKinds.includes(event.payload?.kind)) {
+      loadArchivedNotes(true);
+    }
+  });
 });
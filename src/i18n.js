@@ -21,6 +21,13 @@
       'note.saved': 'Saved',
       'note.saving': 'Saving...',
       'note.saveFailed': 'Save failed',
+      'note.truncated': 'Too large, read-only preview',
+      'note.locked': 'Locked',
+      'note.unlock': 'Unlock',
+      'note.unlockTitle': 'This note is protected',
+      'note.unlockMessage': 'Enter the passphrase to open it.',
+      'note.unlockFailed': 'Wrong passphrase',
+      'note.peek': 'Archived, read-only',
       'note.loadFailed': 'Load failed',
       'note.placeholder': 'Write something...',
       'note.newTitle': 'New Note',
@@ -102,6 +109,13 @@
       'note.saved': '已保存',
       'note.saving': '保存中...',
       'note.saveFailed': '保存失败',
+      'note.truncated': '内容过大，仅预览',
+      'note.locked': '已锁定',
+      'note.unlock': '解锁',
+      'note.unlockTitle': '这个便签受口令保护',
+      'note.unlockMessage': '输入口令后打开。',
+      'note.unlockFailed': '口令错误',
+      'note.peek': '已归档，只读',
       'note.loadFailed': '加载失败',
       'note.placeholder': '写点什么...',
       'note.newTitle': '新便签',
@@ -72,6 +72,58 @@ async function showCustomConfirm(title, message) {
   });
 }
 
+// 输入口令后弹出口令框，确认时返回输入的口令，取消时返回 null
+async function showPassphrasePrompt(title, message, error = '') {
+  return new Promise((resolve) => {
+    const overlay = document.createElement('div');
+    overlay.style.cssText = `
+      position:fixed;inset:0;display:flex;justify-content:center;align-items:center;
+      background:rgba(0,0,0,0.25);backdrop-filter:blur(3px);z-index:10000;
+    `;
+
+    const dialog = document.createElement('div');
+    dialog.style.cssText = `
+      background:#fffdf5;border-radius:12px;padding:22px 24px;width:280px;max-width:85%;
+      box-shadow:0 20px 40px rgba(0,0,0,0.15);border:1px solid #e8e2d6;
+      font-family:-apple-system,BlinkMacSystemFont,'Segoe UI',Roboto,sans-serif;
+    `;
+
+    dialog.innerHTML = `
+      <div style="text-align:center;margin-bottom:14px;">
+        <div style="font-size:16px;font-weight:600;color:#333;margin-bottom:6px;">${title}</div>
+        <div style="font-size:13px;color:#888;">${message}</div>
+      </div>
+      <input id="passphrase-input" type="password" autocomplete="off"
+        style="width:100%;box-sizing:border-box;padding:6px 8px;border:1px solid #e8e2d6;border-radius:6px;font-size:14px;margin-bottom:6px;" />
+      <div style="font-size:12px;color:#d9534f;min-height:16px;margin-bottom:10px;">${escapeHtml(error)}</div>
+      <div style="display:flex;justify-content:flex-end;gap:12px;">
+        <button id="passphrase-cancel-btn" style="background:none;border:none;color:#666;font-size:14px;cursor:pointer;">${tr('common.cancel')}</button>
+        <button id="passphrase-confirm-btn" style="background:none;border:none;color:#333;font-size:14px;font-weight:500;cursor:pointer;">${tr('note.unlock')}</button>
+      </div>
+    `;
+
+    overlay.appendChild(dialog);
+    document.body.appendChild(overlay);
+    const input = dialog.querySelector('#passphrase-input');
+    input.focus();
+
+    function closeDialog(result) {
+      document.removeEventListener('keydown', keyHandler);
+      document.body.removeChild(overlay);
+      resolve(result);
+    }
+
+    function keyHandler(e) {
+      if (e.key === 'Escape') closeDialog(null);
+      if (e.key === 'Enter') closeDialog(input.value);
+    }
+
+    dialog.querySelector('#passphrase-cancel-btn').addEventListener('click', () => closeDialog(null));
+    dialog.querySelector('#passphrase-confirm-btn').addEventListener('click', () => closeDialog(input.value));
+    document.addEventListener('keydown', keyHandler);
+  });
+}
+
 let noteId = null;
 let noteIdSet = false;
 let hasInitialized = false;
@@ -84,6 +136,17 @@ let isRendering = false;
 let isComposing = false;
 let currentNoteDetail = null;
 let isClosingWindow = false;
+// 超大便签只加载了前一部分，禁止保存以免覆盖完整内容
+let isTruncated = false;
+// 受保护且未解锁的便签没有加载正文，同样禁止保存
+let isLocked = false;
+// unlock_note 发放的解锁令牌，读写受保护便签的正文时带上
+let unlockToken = null;
+// 贴边自动隐藏的边缘；开启时窗口位置由边缘决定，不再保存
+let autoHideEdge = null;
+// 纯文本模式下不解释 Markdown，每行原样显示和保存
+let isMarkdownMode = true;
+let autoHideTimer = null;
 
 const urlParams = new URLSearchParams(window.location.search);
 const urlNoteId = urlParams.get('noteId');
@@ -91,6 +154,8 @@ if (urlNoteId) {
   noteId = urlNoteId;
   noteIdSet = true;
 }
+// 归档便签的只读预览窗口：不保存内容和窗口状态
+const isPeek = urlParams.get('peek') === '1';
 
 function escapeHtml(value) {
   return String(value)
@@ -162,6 +227,10 @@ async function updateWindowTitle() {
 }
 
 function parseMarkdownLine(line) {
+  if (!isMarkdownMode) {
+    return { type: 'paragraph', checked: false, content: line || '' };
+  }
+
   const taskMatch = line.match(/^(\s*)- \[([ xX])\]\s?(.*)$/);
   if (taskMatch) {
     return {
@@ -229,6 +298,7 @@ function parseInlineMarkdown(content) {
 }
 
 function renderInlineMarkdown(content) {
+  if (!isMarkdownMode) return escapeHtml(content) || '<br>';
   return parseInlineMarkdown(content)
     .map((fragment) => {
       const text = escapeHtml(fragment.text);
@@ -462,6 +532,46 @@ function markdownToVisibleOffset(content, markdownOffset) {
   return visible;
 }
 
+async function loadNoteContent() {
+  try {
+    const savedNote = await window.__TAURI__.core.invoke('load_note', { id: noteId, unlockToken, peek: isPeek });
+    isTruncated = Boolean(savedNote?.truncated);
+    isLocked = Boolean(savedNote?.locked);
+    setMarkdownSource(savedNote?.content || "", false);
+    if (isLocked) {
+      setSaveStatus('error', tr('note.locked'));
+    } else if (isPeek) {
+      setSaveStatus('error', tr('note.peek'));
+    } else if (isTruncated) {
+      setSaveStatus('error', tr('note.truncated'));
+    } else {
+      setSaveStatus('saved', tr('note.saved'));
+    }
+    updateLifecycleStatus();
+  } catch (err) {
+    console.warn('Failed to load note content:', err);
+    setMarkdownSource("", false);
+    setSaveStatus('error', tr('note.loadFailed'));
+  }
+}
+
+// 锁定的便签弹出口令框，解锁成功后带着令牌重新加载正文；口令错误时重新询问，取消则保持锁定
+async function promptUnlock() {
+  let error = '';
+  while (isLocked) {
+    const passphrase = await showPassphrasePrompt(tr('note.unlockTitle'), tr('note.unlockMessage'), error);
+    if (passphrase === null) return;
+    try {
+      unlockToken = await window.__TAURI__.core.invoke('unlock_note', { id: noteId, passphrase });
+    } catch (err) {
+      console.warn('Failed to unlock note:', err);
+      error = tr('note.unlockFailed');
+      continue;
+    }
+    await loadNoteContent();
+  }
+}
+
 function scheduleAutoSave() {
   if (idleTimer) clearTimeout(idleTimer);
   setSaveStatus('saving', tr('note.saving'));
@@ -480,7 +590,7 @@ function scheduleAutoSave() {
 }
 
 async function saveCurrentNoteContent({ touchActivity = true } = {}) {
-  if (!noteId || !editor) return;
+  if (!noteId || !editor || isTruncated || isLocked || isPeek) return;
 
   if (idleTimer) {
     clearTimeout(idleTimer);
@@ -491,7 +601,8 @@ async function saveCurrentNoteContent({ touchActivity = true } = {}) {
   setSaveStatus('saving', tr('note.saving'));
   await window.__TAURI__.core.invoke(touchActivity ? 'save_note_content' : 'save_note_content_without_touch', {
     id: noteId,
-    content: markdownSource
+    content: markdownSource,
+    unlockToken
   });
   if (touchActivity) await updateWindowTitle();
   setSaveStatus('saved', tr('note.saved'));
@@ -536,7 +647,7 @@ async function closeAfterSaving({ touchActivity = true, destroy = false } = {})
 }
 
 async function saveWindowState() {
-  if (!noteId) return;
+  if (!noteId || isPeek) return;
   const position = await win.innerPosition();
   const size = await win.innerSize();
   await window.__TAURI__.core.invoke('update_note_window', {
@@ -549,7 +660,7 @@ async function saveWindowState() {
 }
 
 function scheduleWindowStateSave() {
-  if (!noteId || isClosingWindow) return;
+  if (!noteId || isClosingWindow || autoHideEdge) return;
   if (windowStateTimer) clearTimeout(windowStateTimer);
 
   windowStateTimer = setTimeout(async () => {
@@ -958,6 +1069,49 @@ function initializeNewNoteGesture() {
   });
 }
 
+// 鼠标移入时滑回屏幕内，移出稍等片刻再滑出；平台不支持移动窗口时只记录警告
+function slideNoteWindow(hidden) {
+  if (!noteId || !autoHideEdge) return;
+  window.__TAURI__.core.invoke('slide_note_window', { id: noteId, hidden }).catch((err) => {
+    console.warn('Failed to slide note window:', err);
+  });
+}
+
+function initializeAutoHide() {
+  document.documentElement.addEventListener('mouseenter', () => {
+    if (autoHideTimer) clearTimeout(autoHideTimer);
+    autoHideTimer = null;
+    slideNoteWindow(false);
+  });
+  document.documentElement.addEventListener('mouseleave', () => {
+    if (!autoHideEdge) return;
+    if (autoHideTimer) clearTimeout(autoHideTimer);
+    autoHideTimer = setTimeout(() => {
+      autoHideTimer = null;
+      slideNoteWindow(true);
+    }, 400);
+  });
+}
+
+function applyFontSize(fontSize) {
+  editor.style.fontSize = fontSize ? `${fontSize}px` : '';
+}
+
+// 未设置的颜色移除覆盖，回到主题颜色
+function applyNoteColors(colors) {
+  const root = document.documentElement.style;
+  if (colors?.bgColor) {
+    root.setProperty('--paper-bg', colors.bgColor);
+  } else {
+    root.removeProperty('--paper-bg');
+  }
+  if (colors?.textColor) {
+    root.setProperty('--paper-text', colors.textColor);
+  } else {
+    root.removeProperty('--paper-text');
+  }
+}
+
 function initializeLifecycleEvents() {
   win.listen('fadenote://archive-now', async () => {
     if (!noteId) return;
@@ -966,6 +1120,51 @@ function initializeLifecycleEvents() {
     console.warn('Failed to listen for lifecycle events:', err);
   });
 
+  win.listen('fadenote://save-now', async () => {
+    if (!noteId) return;
+    try {
+      await saveCurrentNoteContent({ touchActivity: false });
+    } catch (err) {
+      console.error('Failed to save note content:', err);
+      setSaveStatus('error', tr('note.saveFailed'));
+    }
+  }).catch((err) => {
+    console.warn('Failed to listen for save requests:', err);
+  });
+
+  // 换边和取消时的滑动由后端完成
+  win.listen('fadenote://auto-hide-edge-changed', async (event) => {
+    if (event.payload) {
+      autoHideEdge = event.payload;
+    } else if (autoHideEdge) {
+      // 后端已把窗口滑回屏幕内，保存复位后的位置
+      autoHideEdge = null;
+      scheduleWindowStateSave();
+    }
+  }).catch((err) => {
+    console.warn('Failed to listen for auto-hide changes:', err);
+  });
+
+  win.listen('fadenote://markdown-mode-changed', (event) => {
+    markdownSource = readMarkdownFromEditor();
+    isMarkdownMode = Boolean(event.payload);
+    renderMarkdown(true);
+  }).catch((err) => {
+    console.warn('Failed to listen for markdown mode changes:', err);
+  });
+
+  win.listen('fadenote://font-size-changed', (event) => {
+    applyFontSize(event.payload);
+  }).catch((err) => {
+    console.warn('Failed to listen for font size changes:', err);
+  });
+
+  win.listen('fadenote://colors-changed', (event) => {
+    applyNoteColors(event.payload);
+  }).catch((err) => {
+    console.warn('Failed to listen for color changes:', err);
+  });
+
   win.onCloseRequested(async (event) => {
     if (isClosingWindow) return;
     event.preventDefault();
@@ -1014,26 +1213,29 @@ document.addEventListener('DOMContentLoaded', async () => {
     const activeNotes = await window.__TAURI__.core.invoke('get_active_notes');
     const noteDetail = activeNotes.find(note => note.id === noteId);
     currentNoteDetail = noteDetail || null;
+    applyFontSize(noteDetail?.fontSize);
+    applyNoteColors(noteDetail);
     if (noteDetail?.window) {
       await win.setPosition(new window.__TAURI__.window.Position(noteDetail.window.x, noteDetail.window.y));
       await win.setSize(new window.__TAURI__.window.Size(noteDetail.window.width, noteDetail.window.height));
     }
+    autoHideEdge = noteDetail?.autoHideEdge || null;
+    isMarkdownMode = noteDetail?.markdown !== false;
+    initializeAutoHide();
+    slideNoteWindow(true);
     updateLifecycleStatus();
     await updatePinStatus();
   } catch (err) {
     console.warn('Failed to get note position info:', err);
   }
 
-  try {
-    const savedContent = await window.__TAURI__.core.invoke('load_note', { id: noteId });
-    setMarkdownSource(savedContent || "", false);
-    setSaveStatus('saved', tr('note.saved'));
-    updateLifecycleStatus();
-  } catch (err) {
-    console.warn('Failed to load note content:', err);
-    setMarkdownSource("", false);
-    setSaveStatus('error', tr('note.loadFailed'));
-  }
+  await loadNoteContent();
+  await promptUnlock();
+
+  // 取消口令框后，点击状态点可以重新解锁
+  saveStatus?.addEventListener('click', () => {
+    if (isLocked) promptUnlock();
+  });
 
   window.addEventListener('beforeunload', () => {
     try {
@@ -25,6 +25,7 @@ function updateWeekdayVisibility() {
 function readForm() {
   const selectedTheme = themeInput.value || 'paper';
   return {
+    ...(currentSettings || {}),
     enabled: enabledInput.checked,
     time: timeInput.value || '09:00',
     recurrence: recurrenceInput.value,